@@ -0,0 +1,44 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use smrng::{
+    drops::{self, DropSet},
+    Rng,
+};
+
+// Rough timings on a modern desktop CPU, for spotting large regressions:
+//   analyze_reset:             ~1.2 ms
+//   analyze_correlated_all:    ~3 ms
+//   roll_multiple_metroid:     ~400 ns
+
+fn analyze(c: &mut Criterion) {
+    c.bench_function("analyze_reset", |b| {
+        b.iter(|| black_box(Rng::RESET).analyze())
+    });
+}
+
+fn drops(c: &mut Criterion) {
+    let sidehopper = drops::lookup_enemy("sidehopperBlue").unwrap();
+    c.bench_function("analyze_correlated_all", |b| {
+        b.iter(|| {
+            drops::analysis::analyze_correlated(
+                sidehopper,
+                &DropSet::ALL,
+                1,
+                Rng::RESET,
+                0..=u16::MAX,
+            )
+        })
+    });
+
+    let metroid = drops::lookup_enemy("metroid").unwrap();
+    c.bench_function("roll_multiple_metroid", |b| {
+        b.iter(|| {
+            let mut rng = Rng::RESET;
+            metroid
+                .roll_multiple(&mut rng, &DropSet::ALL, black_box(1))
+                .count()
+        })
+    });
+}
+
+criterion_group!(benches, analyze, drops);
+criterion_main!(benches);
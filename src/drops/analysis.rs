@@ -1,11 +1,16 @@
+use std::{
+    collections::HashMap,
+    ops::{Add, AddAssign},
+};
+
 use serde::Serialize;
 
 use crate::Rng;
 
-use super::{Drop, DropSet, DropTable};
+use super::{Drop, DropError, DropSet, DropTable};
 
 /// The simulated results of farming an enemy across a set of seeds.
-#[derive(Default, PartialEq, Eq, Hash, Serialize, Debug)]
+#[derive(Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Debug)]
 pub struct DropAnalysis {
     /// The number of seeds sampled.
     pub seeds: u32,
@@ -19,6 +24,81 @@ pub struct DropAnalysis {
 }
 
 impl DropAnalysis {
+    /// Analyses of fewer seeds than this are considered too small to be statistically reliable.
+    pub const MIN_RELIABLE_SEEDS: u32 = 20;
+
+    /// Returns the number of times `drop` was dropped.
+    pub fn get(&self, drop: Drop) -> u32 {
+        match drop {
+            Drop::Nothing => self.nothing,
+            Drop::SmallEnergy => self.small_energy,
+            Drop::BigEnergy => self.big_energy,
+            Drop::Missile => self.missile,
+            Drop::SuperMissile => self.super_missile,
+            Drop::PowerBomb => self.power_bomb,
+        }
+    }
+
+    /// Returns the mean number of times `drop` was dropped per seed, or 0 if no seeds were
+    /// sampled.
+    pub fn rate(&self, drop: Drop) -> f32 {
+        if self.seeds == 0 {
+            0.
+        } else {
+            self.get(drop) as f32 / self.seeds as f32
+        }
+    }
+
+    /// Returns the fraction of the drops generated that were `drop`, or 0 if no drops were
+    /// generated.
+    ///
+    /// For enemies that drop one item per kill, this is the chance of `drop` per kill. Unlike
+    /// `rate`, the probabilities of every drop (including `Drop::Nothing`) sum to 1.
+    pub fn probability(&self, drop: Drop) -> f32 {
+        match self.total_drops() {
+            0 => 0.,
+            total => self.get(drop) as f32 / total as f32,
+        }
+    }
+
+    /// Returns the total number of drops generated, including `Drop::Nothing`.
+    pub fn total_drops(&self) -> u32 {
+        Drop::ALL.iter().map(|&drop| self.get(drop)).sum()
+    }
+
+    /// Returns a warning message if too few seeds were sampled for the results to be reliable.
+    pub fn sample_size_warning(&self) -> Option<String> {
+        (self.seeds < Self::MIN_RELIABLE_SEEDS).then(|| {
+            format!(
+                "Note: these results are based on a small sample of only {} seed{}",
+                self.seeds,
+                if self.seeds == 1 { "" } else { "s" }
+            )
+        })
+    }
+
+    /// Returns the header row for CSV output, naming the columns written by `to_csv_row`.
+    ///
+    /// The columns match the fields of the JSON output, so aggregate analyses and histogram
+    /// entries share one schema.
+    pub fn csv_header() -> &'static str {
+        "seeds,nothing,small_energy,big_energy,missile,super_missile,power_bomb"
+    }
+
+    /// Formats this analysis as a row of CSV output, without a trailing newline.
+    pub fn to_csv_row(&self) -> String {
+        format!(
+            "{},{},{},{},{},{},{}",
+            self.seeds,
+            self.nothing,
+            self.small_energy,
+            self.big_energy,
+            self.missile,
+            self.super_missile,
+            self.power_bomb
+        )
+    }
+
     fn update(&mut self, drop: Drop) {
         match drop {
             Drop::Nothing => self.nothing += 1,
@@ -31,6 +111,94 @@ impl DropAnalysis {
     }
 }
 
+/// Combines two analyses of disjoint sets of seeds, as if they had been analyzed together.
+impl Add for DropAnalysis {
+    type Output = DropAnalysis;
+
+    fn add(mut self, rhs: DropAnalysis) -> DropAnalysis {
+        self += rhs;
+        self
+    }
+}
+
+impl AddAssign for DropAnalysis {
+    fn add_assign(&mut self, rhs: DropAnalysis) {
+        self.seeds += rhs.seeds;
+        self.nothing += rhs.nothing;
+        self.small_energy += rhs.small_energy;
+        self.big_energy += rhs.big_energy;
+        self.missile += rhs.missile;
+        self.super_missile += rhs.super_missile;
+        self.power_bomb += rhs.power_bomb;
+    }
+}
+
+/// The mean number of each drop per kill, weighted across a set of seeds.
+#[derive(Clone, Default, PartialEq, Serialize, Debug)]
+pub struct DropRates {
+    pub nothing: f64,
+    pub small_energy: f64,
+    pub big_energy: f64,
+    pub missile: f64,
+    pub super_missile: f64,
+    pub power_bomb: f64,
+}
+
+impl DropRates {
+    /// Returns the mean number of times `drop` is dropped per kill.
+    pub fn get(&self, drop: Drop) -> f64 {
+        match drop {
+            Drop::Nothing => self.nothing,
+            Drop::SmallEnergy => self.small_energy,
+            Drop::BigEnergy => self.big_energy,
+            Drop::Missile => self.missile,
+            Drop::SuperMissile => self.super_missile,
+            Drop::PowerBomb => self.power_bomb,
+        }
+    }
+
+    fn get_mut(&mut self, drop: Drop) -> &mut f64 {
+        match drop {
+            Drop::Nothing => &mut self.nothing,
+            Drop::SmallEnergy => &mut self.small_energy,
+            Drop::BigEnergy => &mut self.big_energy,
+            Drop::Missile => &mut self.missile,
+            Drop::SuperMissile => &mut self.super_missile,
+            Drop::PowerBomb => &mut self.power_bomb,
+        }
+    }
+}
+
+/// Generates `DropRates` for a set of seeds, each with a weight (such as the counts from
+/// `Analysis::visit_counts`), simulating the actual RNG behavior.
+///
+/// With equal weights, this matches the rates from `analyze_correlated`.
+pub fn analyze_weighted(
+    table: &DropTable,
+    possible_drops: &DropSet,
+    n: u32,
+    rng: Rng,
+    seeds: impl IntoIterator<Item = (u16, f64)>,
+) -> Result<DropRates, DropError> {
+    let mut rates = DropRates::default();
+    let mut total_weight = 0.;
+
+    for (seed, weight) in seeds {
+        let mut rng = rng.with_seed(seed);
+        for drop in table.try_roll_multiple(&mut rng, possible_drops, n)? {
+            *rates.get_mut(drop) += weight;
+        }
+        total_weight += weight;
+    }
+
+    if total_weight > 0. {
+        for drop in DropSet::ALL {
+            *rates.get_mut(drop) /= total_weight;
+        }
+    }
+    Ok(rates)
+}
+
 /// Generates a `DropAnalysis` for a set of seeds, simulating the actual RNG behavior (including
 /// correlation between successive calls).
 pub fn analyze_correlated(
@@ -39,18 +207,291 @@ pub fn analyze_correlated(
     n: u32,
     rng: Rng,
     seeds: impl IntoIterator<Item = u16>,
-) -> DropAnalysis {
+) -> Result<DropAnalysis, DropError> {
     let mut analysis = DropAnalysis::default();
 
     for seed in seeds {
         let mut rng = rng.with_seed(seed);
-        for drop in table.roll_multiple(&mut rng, possible_drops, n) {
+        for drop in table.try_roll_multiple(&mut rng, possible_drops, n)? {
+            analysis.update(drop);
+        }
+
+        analysis.seeds += 1;
+    }
+    Ok(analysis)
+}
+
+/// Generates a `DropAnalysis` for `kills` consecutive kills, simulating uninterrupted farming
+/// starting from the current state of `rng`.
+///
+/// Unlike `analyze_correlated`, which resets the RNG to each seed before simulating a kill, this
+/// carries the RNG state from one kill to the next: the RNG calls made while generating a kill's
+/// drops are not undone, and one frame advance separates each kill. The kills therefore sample
+/// the seeds the game actually visits while farming, rather than every seed equally.
+pub fn analyze_continuous(
+    table: &DropTable,
+    possible_drops: &DropSet,
+    n: u32,
+    mut rng: Rng,
+    kills: u32,
+) -> Result<DropAnalysis, DropError> {
+    let mut analysis = DropAnalysis::default();
+
+    for _ in 0..kills {
+        for drop in table.try_roll_multiple(&mut rng, possible_drops, n)? {
             analysis.update(drop);
         }
+        rng.frame_advance();
+
+        analysis.seeds += 1;
+    }
+    Ok(analysis)
+}
+
+/// Generates a separate `DropAnalysis` for each seed in a set, simulating the actual RNG behavior
+/// (including correlation between successive calls).
+pub fn per_seed(
+    table: &DropTable,
+    possible_drops: &DropSet,
+    n: u32,
+    rng: Rng,
+    seeds: impl IntoIterator<Item = u16>,
+) -> Result<Vec<(u16, DropAnalysis)>, DropError> {
+    seeds
+        .into_iter()
+        .map(|seed| {
+            let analysis =
+                analyze_correlated(table, possible_drops, n, rng.clone(), std::iter::once(seed))?;
+            Ok((seed, analysis))
+        })
+        .collect()
+}
+
+/// Groups a set of seeds by the drops they produce, returning one `DropAnalysis` per distinct
+/// outcome with `seeds` set to the number of seeds that produce it.
+///
+/// Only the drops in `counted` are tallied, so seeds whose drops differ only in other drops are
+/// grouped together. Outcomes are sorted by descending number of seeds, with ties broken by the
+/// drop counts.
+pub fn histogram(
+    table: &DropTable,
+    possible_drops: &DropSet,
+    n: u32,
+    rng: Rng,
+    seeds: impl IntoIterator<Item = u16>,
+    counted: &DropSet,
+) -> Result<Vec<DropAnalysis>, DropError> {
+    let mut histogram = HashMap::new();
+    for seed in seeds {
+        let outcome = outcome(table, possible_drops, n, &rng, seed, counted)?;
+        *histogram.entry(outcome).or_default() += 1;
+    }
+    Ok(sort_histogram(histogram))
+}
+
+/// Like `histogram`, but simulates the seeds in parallel. The result is identical.
+#[cfg(feature = "rayon")]
+pub fn par_histogram(
+    table: &DropTable,
+    possible_drops: &DropSet,
+    n: u32,
+    rng: Rng,
+    seeds: &[u16],
+    counted: &DropSet,
+) -> Result<Vec<DropAnalysis>, DropError> {
+    use rayon::prelude::*;
+
+    let histogram = seeds
+        .par_iter()
+        .try_fold(HashMap::new, |mut histogram, &seed| {
+            let outcome = outcome(table, possible_drops, n, &rng, seed, counted)?;
+            *histogram.entry(outcome).or_default() += 1;
+            Ok(histogram)
+        })
+        .try_reduce(HashMap::new, |mut a, b| {
+            for (outcome, count) in b {
+                *a.entry(outcome).or_default() += count;
+            }
+            Ok(a)
+        })?;
+    Ok(sort_histogram(histogram))
+}
+
+/// Simulates a single seed for `histogram`, zeroing the drops not in `counted`.
+fn outcome(
+    table: &DropTable,
+    possible_drops: &DropSet,
+    n: u32,
+    rng: &Rng,
+    seed: u16,
+    counted: &DropSet,
+) -> Result<DropAnalysis, DropError> {
+    let mut analysis =
+        analyze_correlated(table, possible_drops, n, rng.clone(), std::iter::once(seed))?;
+    let zero_unless = |drop, count: &mut u32| {
+        if !counted.contains(&drop) {
+            *count = 0;
+        }
+    };
+    zero_unless(Drop::Nothing, &mut analysis.nothing);
+    zero_unless(Drop::SmallEnergy, &mut analysis.small_energy);
+    zero_unless(Drop::BigEnergy, &mut analysis.big_energy);
+    zero_unless(Drop::Missile, &mut analysis.missile);
+    zero_unless(Drop::SuperMissile, &mut analysis.super_missile);
+    zero_unless(Drop::PowerBomb, &mut analysis.power_bomb);
+    Ok(analysis)
+}
+
+fn sort_histogram(histogram: HashMap<DropAnalysis, u32>) -> Vec<DropAnalysis> {
+    let mut histogram: Vec<_> = histogram
+        .into_iter()
+        .map(|(entry, count)| DropAnalysis {
+            seeds: count,
+            ..entry
+        })
+        .collect();
+    histogram.sort_by(|a, b| b.seeds.cmp(&a.seeds).then_with(|| a.cmp(b)));
+    histogram
+}
+
+/// Generates a `DropAnalysis` for a farming route across a set of seeds, simulating the actual
+/// RNG behavior (including correlation between successive calls).
+///
+/// For each seed, every `(table, n)` pair in `tables` is farmed in order, as if `n` of that enemy
+/// were killed at once; all enemies share the same RNG state.
+pub fn analyze_route(
+    tables: &[(&DropTable, u32)],
+    possible_drops: &DropSet,
+    rng: Rng,
+    seeds: impl IntoIterator<Item = u16>,
+) -> Result<DropAnalysis, DropError> {
+    let mut analysis = DropAnalysis::default();
+
+    for seed in seeds {
+        let mut rng = rng.with_seed(seed);
+        for &(table, n) in tables {
+            for drop in table.try_roll_multiple(&mut rng, possible_drops, n)? {
+                analysis.update(drop);
+            }
+        }
 
         analysis.seeds += 1;
     }
-    analysis
+    Ok(analysis)
+}
+
+/// Returns the fraction of seeds for which farming the enemy produces at least one `drop`,
+/// simulating the actual RNG behavior (including correlation between successive calls).
+pub fn chance_at_least_one(
+    table: &DropTable,
+    possible_drops: &DropSet,
+    n: u32,
+    rng: Rng,
+    seeds: impl IntoIterator<Item = u16>,
+    drop: Drop,
+) -> Result<f32, DropError> {
+    if !possible_drops.contains(&drop) {
+        return Ok(0.);
+    }
+
+    let mut num_seeds = 0;
+    let mut hits = 0;
+    for seed in seeds {
+        let mut rng = rng.with_seed(seed);
+        if table
+            .try_roll_multiple(&mut rng, possible_drops, n)?
+            .any(|d| d == drop)
+        {
+            hits += 1;
+        }
+        num_seeds += 1;
+    }
+
+    if num_seeds == 0 {
+        Ok(0.)
+    } else {
+        Ok(hits as f32 / num_seeds as f32)
+    }
+}
+
+/// Returns the fraction of seeds for which farming the enemy produces nothing at all,
+/// simulating the actual RNG behavior (including correlation between successive calls).
+pub fn chance_nothing(
+    table: &DropTable,
+    possible_drops: &DropSet,
+    n: u32,
+    rng: Rng,
+    seeds: impl IntoIterator<Item = u16>,
+) -> Result<f32, DropError> {
+    let mut num_seeds = 0;
+    let mut duds = 0;
+    for seed in seeds {
+        let mut rng = rng.with_seed(seed);
+        if table
+            .try_roll_multiple(&mut rng, possible_drops, n)?
+            .all(|d| d == Drop::Nothing)
+        {
+            duds += 1;
+        }
+        num_seeds += 1;
+    }
+
+    if num_seeds == 0 {
+        Ok(0.)
+    } else {
+        Ok(duds as f32 / num_seeds as f32)
+    }
+}
+
+/// The maximum number of kills `expected_frames_to_drop` simulates per seed before giving up.
+const MAX_KILLS: u32 = 1000;
+
+/// Returns the expected number of frames spent farming the enemy until it drops `drop`, averaged
+/// across a set of seeds and simulating the actual RNG behavior (including correlation between
+/// successive calls).
+///
+/// Each kill is assumed to take `frames_per_kill` frames, including the kill that produces the
+/// drop. Returns `f32::INFINITY` if `drop` can't be produced, or if any seed fails to produce
+/// it within a reasonable number of kills.
+pub fn expected_frames_to_drop(
+    table: &DropTable,
+    possible_drops: &DropSet,
+    rng: Rng,
+    seeds: impl IntoIterator<Item = u16>,
+    drop: Drop,
+    frames_per_kill: u32,
+) -> Result<f32, DropError> {
+    if !possible_drops.contains(&drop) {
+        return Ok(f32::INFINITY);
+    }
+
+    let mut num_seeds = 0;
+    let mut total_kills = 0u64;
+    for seed in seeds {
+        let mut rng = rng.with_seed(seed);
+        let mut kills = 1;
+        while !table
+            .try_roll_multiple(&mut rng, possible_drops, 1)?
+            .any(|d| d == drop)
+        {
+            if kills == MAX_KILLS {
+                return Ok(f32::INFINITY);
+            }
+            kills += 1;
+            for _ in 0..frames_per_kill {
+                rng.frame_advance();
+            }
+        }
+
+        total_kills += kills as u64;
+        num_seeds += 1;
+    }
+
+    if num_seeds == 0 {
+        Ok(0.)
+    } else {
+        Ok(total_kills as f32 * frames_per_kill as f32 / num_seeds as f32)
+    }
 }
 
 /// Generates a `DropAnalysis` for a set of seeds; simulating RNG distribution across the given set
@@ -60,7 +501,7 @@ pub fn analyze_uncorrelated<S: IntoIterator<Item = u16>>(
     possible_drops: &DropSet,
     n: u32,
     seeds: S,
-) -> DropAnalysis
+) -> Result<DropAnalysis, DropError>
 where
     S::IntoIter: ExactSizeIterator + Clone,
 {
@@ -69,13 +510,12 @@ where
     let num_seeds = seeds.len() as u32;
     analysis.seeds = num_seeds;
 
-    let drop_count = table.count.unwrap_or(1) + table.extra.as_ref().map(|_| 1).unwrap_or(0);
+    let drop_count = table.num_drops();
 
-    seeds
-        .cycle()
-        .take((num_seeds * drop_count * n) as usize)
-        .map(|seed| Rng::RESET.with_seed(seed))
-        .for_each(|mut rng| analysis.update(table.roll_one(&mut rng, possible_drops)));
+    for seed in seeds.cycle().take((num_seeds * drop_count * n) as usize) {
+        let mut rng = Rng::RESET.with_seed(seed);
+        analysis.update(table.try_roll_one(&mut rng, possible_drops)?);
+    }
 
-    analysis
+    Ok(analysis)
 }
@@ -2,22 +2,131 @@ pub mod analysis;
 
 use std::{
     collections::HashMap,
+    error::Error,
+    fmt::{self, Display, Formatter},
     ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Index, Sub, SubAssign},
-    sync::LazyLock,
+    str::FromStr,
+    sync::{LazyLock, RwLock},
 };
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
+use self::analysis::DropRates;
 use crate::Rng;
 
 const ENEMY_DROPS_JSON: &str = include_str!("enemy_drops.json");
 
 /// The drop table for enemies in vanilla SM.
-pub static ENEMY_DROPS: LazyLock<HashMap<String, DropTable>> =
-    LazyLock::new(|| serde_json::from_str(ENEMY_DROPS_JSON).unwrap());
+pub static ENEMY_DROPS: LazyLock<HashMap<String, DropTable>> = LazyLock::new(|| {
+    load_enemy_drops(ENEMY_DROPS_JSON)
+        .unwrap_or_else(|e| panic!("invalid built-in drop tables: {e}"))
+});
+
+/// Parses a set of enemy drop tables from JSON, in the same format as the built-in table: an
+/// object mapping enemy names to drop tables.
+///
+/// Every table is checked with `DropTable::validate`, so a table that can't be used to generate
+/// drops is reported as an error rather than being accepted.
+pub fn load_enemy_drops(json: &str) -> Result<HashMap<String, DropTable>, LoadError> {
+    let drops: HashMap<String, DropTable> = serde_json::from_str(json)?;
+    for (enemy, table) in &drops {
+        table.validate().map_err(|reason| LoadError::InvalidTable {
+            enemy: enemy.clone(),
+            reason,
+        })?;
+    }
+    Ok(drops)
+}
+
+/// An error encountered while loading enemy drop tables with `load_enemy_drops`.
+#[derive(Debug)]
+pub enum LoadError {
+    /// The JSON could not be parsed as a set of drop tables.
+    Json(serde_json::Error),
+
+    /// An enemy's drop table failed `DropTable::validate`.
+    InvalidTable { enemy: String, reason: DropError },
+}
+
+impl Display for LoadError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Json(e) => write!(f, "{e}"),
+            LoadError::InvalidTable { enemy, reason } => {
+                write!(f, "invalid drop table for {enemy}: {reason}")
+            }
+        }
+    }
+}
+
+impl Error for LoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LoadError::Json(e) => Some(e),
+            LoadError::InvalidTable { reason, .. } => Some(reason),
+        }
+    }
+}
+
+impl From<serde_json::Error> for LoadError {
+    fn from(e: serde_json::Error) -> Self {
+        LoadError::Json(e)
+    }
+}
+
+/// Drop tables registered at runtime with `register_enemy`.
+static CUSTOM_DROPS: LazyLock<RwLock<HashMap<String, &'static DropTable>>> =
+    LazyLock::new(Default::default);
+
+/// Registers a custom enemy's drop table, for use with modified games.
+///
+/// Custom enemies take precedence over built-in enemies of the same name in `lookup_enemy`.
+/// Registered tables are never freed, so this is intended to be called a bounded number of times
+/// (e.g. during startup). Returns an error without registering anything if the table fails
+/// `DropTable::validate`.
+pub fn register_enemy(name: String, table: DropTable) -> Result<(), DropError> {
+    table.validate()?;
+    let table = Box::leak(Box::new(table));
+    CUSTOM_DROPS.write().unwrap().insert(name, table);
+    Ok(())
+}
+
+/// Looks up the drop table for an enemy by name, consulting enemies registered with
+/// `register_enemy` before the built-in `ENEMY_DROPS`.
+pub fn lookup_enemy(name: &str) -> Option<&'static DropTable> {
+    let custom = CUSTOM_DROPS.read().unwrap().get(name).copied();
+    custom.or_else(|| ENEMY_DROPS.get(name))
+}
+
+/// Returns the number of rolls the drop routine discards before choosing a drop, starting from
+/// the current state of `rng`.
+///
+/// The drop routine re-rolls until the low byte of the random number is nonzero, so choosing a
+/// drop takes `1 + zero_rerolls(rng)` RNG calls rather than a fixed number, which shifts the
+/// timing of every later roll.
+pub fn zero_rerolls(rng: &Rng) -> u32 {
+    let mut rng = rng.clone();
+    let mut rerolls = 0;
+    while rng.roll() as u8 == 0 {
+        rerolls += 1;
+    }
+    rerolls
+}
+
+/// Returns the mean of `zero_rerolls` over every seed with `rng`'s parameters: the expected number
+/// of extra rolls per drop.
+///
+/// About 1 in 256 rolls has a low byte of zero, so this is close to 1/255.
+pub fn expected_zero_rerolls(rng: &Rng) -> f64 {
+    let total: u64 = (0..=u16::MAX)
+        .map(|seed| zero_rerolls(&rng.with_seed(seed)) as u64)
+        .sum();
+    total as f64 / 0x10000 as f64
+}
 
 /// A drop type.
-#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Drop {
     Nothing,
     SmallEnergy,
@@ -28,6 +137,16 @@ pub enum Drop {
 }
 
 impl Drop {
+    /// Every drop type, in the order of their bits in a `DropSet`.
+    pub const ALL: [Drop; 6] = [
+        Drop::SmallEnergy,
+        Drop::BigEnergy,
+        Drop::Missile,
+        Drop::Nothing,
+        Drop::SuperMissile,
+        Drop::PowerBomb,
+    ];
+
     /// Whether this is considered a "Tier 2" drop.
     pub const fn is_major(&self) -> bool {
         use self::Drop::*;
@@ -50,33 +169,37 @@ impl Drop {
     }
 
     const fn from_index(index: u8) -> Self {
-        use self::Drop::*;
-        match index {
-            0 => SmallEnergy,
-            1 => BigEnergy,
-            2 => Missile,
-            3 => Nothing,
-            4 => SuperMissile,
-            5 => PowerBomb,
-            _ => panic!("invalid drop index"),
+        Drop::ALL[index as usize]
+    }
+}
+
+impl FromStr for Drop {
+    type Err = String;
+
+    /// Parses a drop name, such as `small_energy` or `pb`, ignoring case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "nothing" => Ok(Drop::Nothing),
+            "small_energy" | "small" => Ok(Drop::SmallEnergy),
+            "big_energy" | "big" => Ok(Drop::BigEnergy),
+            "missile" => Ok(Drop::Missile),
+            "super_missile" | "super" => Ok(Drop::SuperMissile),
+            "power_bomb" | "pb" => Ok(Drop::PowerBomb),
+            _ => Err(format!("unknown drop '{s}'")),
         }
     }
 }
 
 /// A set of drops.
-#[derive(Clone, PartialEq, Eq, Hash)]
+///
+/// Serialized as its bitmask; see `DropSet::bits`.
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+#[serde(into = "u8", try_from = "u8")]
 pub struct DropSet(u8);
 
 impl DropSet {
     pub const EMPTY: DropSet = DropSet::new();
-    pub const ALL: DropSet = DropSet::from_slice(&[
-        Drop::Nothing,
-        Drop::SmallEnergy,
-        Drop::BigEnergy,
-        Drop::Missile,
-        Drop::SuperMissile,
-        Drop::PowerBomb,
-    ]);
+    pub const ALL: DropSet = DropSet::from_slice(&Drop::ALL);
     pub const MINOR: DropSet = DropSet::from_slice(&[
         Drop::Nothing,
         Drop::SmallEnergy,
@@ -86,10 +209,33 @@ impl DropSet {
     pub const MAJOR: DropSet = DropSet::from_slice(&[Drop::SuperMissile, Drop::PowerBomb]);
     pub const HEALTH_BOMB: DropSet = DropSet::from_slice(&[Drop::SmallEnergy, Drop::BigEnergy]);
 
+    /// The ammunition drops: missiles, super missiles, and power bombs.
+    pub const AMMO: DropSet = DropSet::MAJOR.union(&DropSet::from_slice(&[Drop::Missile]));
+
+    /// The energy drops: small and big energy. The same set as `HEALTH_BOMB`.
+    pub const ENERGY: DropSet = DropSet::ALL
+        .difference(&DropSet::AMMO)
+        .difference(&DropSet::from_slice(&[Drop::Nothing]));
+
     pub const fn new() -> DropSet {
         DropSet(0)
     }
 
+    /// Returns the bitmask representation of this set, with one bit for each drop.
+    pub const fn bits(&self) -> u8 {
+        self.0
+    }
+
+    /// Creates a set from its bitmask representation, or returns `None` if any bits don't
+    /// correspond to a drop.
+    pub const fn from_bits(bits: u8) -> Option<DropSet> {
+        if bits & !DropSet::ALL.0 == 0 {
+            Some(DropSet(bits))
+        } else {
+            None
+        }
+    }
+
     pub const fn is_empty(&self) -> bool {
         self.0 == 0
     }
@@ -130,10 +276,41 @@ impl DropSet {
         DropSet(self.0 ^ other.0)
     }
 
+    /// Returns the drops that are not in this set.
+    pub const fn complement(&self) -> DropSet {
+        DropSet::ALL.difference(self)
+    }
+
+    pub const fn is_subset(&self, other: &DropSet) -> bool {
+        self.0 & !other.0 == 0
+    }
+
+    pub const fn is_superset(&self, other: &DropSet) -> bool {
+        other.is_subset(self)
+    }
+
     pub fn iter(&self) -> impl Iterator<Item = Drop> + '_ {
         DropSetIterator(self.clone())
     }
 
+    /// Iterates over the drops in this set in a predictable order, suitable for display: the
+    /// minor drops (small energy, big energy, missile), then the major drops (super missile,
+    /// power bomb), then `Drop::Nothing`.
+    ///
+    /// `iter` is slightly faster, but yields drops in the order of their bits, which puts
+    /// `Drop::Nothing` in the middle.
+    pub fn iter_sorted(&self) -> impl Iterator<Item = Drop> + '_ {
+        const ORDER: [Drop; 6] = [
+            Drop::SmallEnergy,
+            Drop::BigEnergy,
+            Drop::Missile,
+            Drop::SuperMissile,
+            Drop::PowerBomb,
+            Drop::Nothing,
+        ];
+        ORDER.into_iter().filter(|drop| self.contains(drop))
+    }
+
     const fn from_slice(drops: &[Drop]) -> DropSet {
         let mut result = DropSet::new();
         let mut i = 0;
@@ -150,6 +327,46 @@ impl Default for DropSet {
     }
 }
 
+impl From<DropSet> for u8 {
+    fn from(set: DropSet) -> u8 {
+        set.bits()
+    }
+}
+
+impl TryFrom<u8> for DropSet {
+    type Error = String;
+
+    fn try_from(bits: u8) -> Result<Self, Self::Error> {
+        DropSet::from_bits(bits).ok_or_else(|| format!("invalid drop set bits {bits:#04x}"))
+    }
+}
+
+impl FromStr for DropSet {
+    type Err = String;
+
+    /// Parses a comma-separated list of drops, such as `small_energy,missile,super_missile`.
+    ///
+    /// In addition to individual drops, accepts `energy` (both energy drops), `ammo` (missiles,
+    /// supers, and power bombs), `minor`, `major`, and `all`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut result = DropSet::new();
+        for token in s.split(',') {
+            let token = token.trim();
+            match token.to_lowercase().as_str() {
+                "energy" => result |= &DropSet::ENERGY,
+                "ammo" => result |= &DropSet::AMMO,
+                "minor" => result |= &DropSet::MINOR,
+                "major" => result |= &DropSet::MAJOR,
+                "all" => result |= &DropSet::ALL,
+                _ => {
+                    result.insert(token.parse()?);
+                }
+            }
+        }
+        Ok(result)
+    }
+}
+
 impl BitAnd<&DropSet> for DropSet {
     type Output = DropSet;
     fn bitand(self, rhs: &DropSet) -> Self::Output {
@@ -234,7 +451,7 @@ impl Iterator for DropSetIterator {
 }
 
 /// A table of an enemy's drop chances.
-#[derive(Deserialize)]
+#[derive(Clone, PartialEq, Eq, Deserialize, Serialize)]
 pub struct DropTable {
     pub nothing: u8,
     pub small_energy: u8,
@@ -244,25 +461,67 @@ pub struct DropTable {
     pub power_bomb: u8,
 
     /// If this enemy calls a multi-drop routine, the number of drops to generate.
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub count: Option<u32>,
 
     /// The type of explosion animation, if this enemy's explosion generates an extra drop.
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub extra: Option<ExplosionDrop>,
 }
 
-#[derive(Deserialize)]
+/// An error encountered while simulating drops from a malformed `DropTable`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DropError {
+    /// The chances of the possible minor drops sum to more than 255.
+    MinorOverflow(u16),
+
+    /// The chances of the possible major drops sum to more than 255.
+    MajorOverflow(u16),
+
+    /// Every drop chance in the table is zero.
+    AllZero,
+}
+
+impl Display for DropError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            DropError::MinorOverflow(sum) => {
+                write!(f, "minor drop chances sum to {sum}, which exceeds 255")
+            }
+            DropError::MajorOverflow(sum) => {
+                write!(f, "major drop chances sum to {sum}, which exceeds 255")
+            }
+            DropError::AllZero => write!(f, "all drop chances are zero"),
+        }
+    }
+}
+
+impl Error for DropError {}
+
+#[derive(Clone, PartialEq, Eq, Deserialize, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum ExplosionDrop {
     Metroid,
     Minikraid,
+
+    /// An arbitrary explosion sequence, for enemies without a preset (such as those in modified
+    /// games).
+    Custom {
+        num_explosions: u32,
+        rng_per_explosion: u32,
+        explosion_interval: u32,
+    },
 }
 
 impl ExplosionDrop {
     /// The number of frames between explosions.
     pub fn explosion_interval(&self) -> u32 {
-        8
+        match self {
+            ExplosionDrop::Metroid | ExplosionDrop::Minikraid => 8,
+            ExplosionDrop::Custom {
+                explosion_interval, ..
+            } => *explosion_interval,
+        }
     }
 
     /// The number of explosions before generating the final drop.
@@ -270,6 +529,7 @@ impl ExplosionDrop {
         match self {
             ExplosionDrop::Metroid => 5,
             ExplosionDrop::Minikraid => 16,
+            ExplosionDrop::Custom { num_explosions, .. } => *num_explosions,
         }
     }
 
@@ -278,6 +538,34 @@ impl ExplosionDrop {
         match self {
             ExplosionDrop::Metroid => 2,
             ExplosionDrop::Minikraid => 3,
+            ExplosionDrop::Custom {
+                rng_per_explosion, ..
+            } => *rng_per_explosion,
+        }
+    }
+
+    /// Simulates the explosion sequence preceding the extra drop, for `n` enemies exploding at
+    /// once.
+    ///
+    /// Each of the `num_explosions` explosions calls RNG `rng_per_explosion` times per enemy, after
+    /// which `explosion_interval` frames elapse before the next explosion. The extra drop is
+    /// generated after the last interval.
+    pub fn simulate(&self, rng: &mut Rng, n: u32) {
+        self.simulate_with_cost(rng, n, &mut RngCost::default())
+    }
+
+    /// Simulates the explosion sequence, adding the RNG calls it consumes to `cost`.
+    fn simulate_with_cost(&self, rng: &mut Rng, n: u32, cost: &mut RngCost) {
+        for _ in 0..self.num_explosions() {
+            for _ in 0..self.rng_per_explosion() * n {
+                rng.roll();
+                cost.rolls += 1;
+            }
+
+            for _ in 0..self.explosion_interval() {
+                rng.frame_advance();
+                cost.frame_advances += 1;
+            }
         }
     }
 }
@@ -298,84 +586,342 @@ impl Index<self::Drop> for DropTable {
 }
 
 impl DropTable {
+    /// Creates a drop table for an enemy that generates a single drop with the given chances.
+    pub const fn new(
+        nothing: u8,
+        small_energy: u8,
+        big_energy: u8,
+        missile: u8,
+        super_missile: u8,
+        power_bomb: u8,
+    ) -> DropTable {
+        DropTable {
+            nothing,
+            small_energy,
+            big_energy,
+            missile,
+            super_missile,
+            power_bomb,
+            count: None,
+            extra: None,
+        }
+    }
+
+    /// Returns this table with the number of drops generated by a multi-drop routine set to
+    /// `count`.
+    pub fn with_count(self, count: u32) -> DropTable {
+        DropTable {
+            count: Some(count),
+            ..self
+        }
+    }
+
+    /// Returns this table with an extra drop generated by the given explosion.
+    pub fn with_explosion(self, extra: ExplosionDrop) -> DropTable {
+        DropTable {
+            extra: Some(extra),
+            ..self
+        }
+    }
+
     /// Returns the raw drop chance for a given drop.
     pub fn get(&self, drop: Drop) -> u8 {
         self[drop]
     }
 
+    /// Returns the sum of the minor drop chances (including nothing).
+    pub fn minor_sum(&self) -> u16 {
+        DropSet::MINOR.iter().map(|d| self[d] as u16).sum()
+    }
+
+    /// Checks that this table's drop chances can be used to generate drops.
+    ///
+    /// Performs the same checks as generating a drop with every drop possible: the chances must
+    /// not all be zero, and neither the minor nor the major chances may sum to more than 255.
+    pub fn validate(&self) -> Result<(), DropError> {
+        self.pools(&DropSet::ALL).map(|_| ())
+    }
+
+    /// Whether this table passes [`DropTable::validate`].
+    pub fn is_valid(&self) -> bool {
+        self.validate().is_ok()
+    }
+
     /// Calculates ideal drops based purely on probabilities in the drop table.
     ///
     /// Returns the expected number of times `drop` will be dropped after farming this enemy
-    /// `farms` times.
+    /// `farms` times. To compute every drop at once, use `expected_drops_all`.
     pub fn ideal_drops_per_farm(&self, drop: Drop, possible_drops: &DropSet, farms: u32) -> f32 {
-        if !possible_drops.contains(&drop) {
+        self.expected_drops_all(possible_drops, farms).get(drop) as f32
+    }
+
+    /// Calculates ideal drops like `ideal_drops_per_farm`, for every drop at once.
+    ///
+    /// This computes the pooled drop chances only once, so it is cheaper than calling
+    /// `ideal_drops_per_farm` for each drop.
+    ///
+    /// Like `roll_one`, any probability not claimed by a possible drop produces `Drop::Nothing`,
+    /// even if `Drop::Nothing` is not itself possible. This includes the remainder left over when
+    /// the scaled minor chances are truncated, and every roll not claimed by a major drop when no
+    /// minor drop can be generated. Over every seed, the correlated simulation therefore agrees
+    /// with these expectations for enemies that generate one drop per kill; for enemies that
+    /// generate several, consecutive drops are correlated and the simulation can differ by a few
+    /// percent.
+    pub fn expected_drops_all(&self, possible_drops: &DropSet, farms: u32) -> DropRates {
+        let pooled_minor = self.pooled_minor(possible_drops);
+        let pooled_major_complement = 0xFF - self.pooled_major(possible_drops);
+        let scale = self.num_drops() as f64 * farms as f64 / 255.;
+
+        let expected = |drop: Drop| {
+            if !possible_drops.contains(&drop) {
+                return 0.;
+            }
+
+            let chance = if !drop.is_major() {
+                // If every minor drop is excluded (or has a zero chance), no minor drop can be
+                // generated; see `roll_one`.
+                (self[drop] as u16 * pooled_major_complement)
+                    .checked_div(pooled_minor)
+                    .unwrap_or(0)
+            } else {
+                self[drop] as u16
+            };
+            chance as f64 * scale
+        };
+
+        let mut rates = DropRates {
+            nothing: 0.,
+            small_energy: expected(Drop::SmallEnergy),
+            big_energy: expected(Drop::BigEnergy),
+            missile: expected(Drop::Missile),
+            super_missile: expected(Drop::SuperMissile),
+            power_bomb: expected(Drop::PowerBomb),
+        };
+        let claimed: f64 = Drop::ALL
+            .into_iter()
+            .filter(|&drop| drop != Drop::Nothing)
+            .map(|drop| rates.get(drop))
+            .sum();
+        rates.nothing = 255. * scale - claimed;
+        rates
+    }
+
+    /// Returns the probability of generating each drop, in the order of `Drop::ALL`, when only
+    /// `possible_drops` can be dropped.
+    ///
+    /// This accounts for the pooling of drop chances and for rolls with a low byte of zero being
+    /// rerolled, so it is the exact probability the simulation in `roll_one` produces given a
+    /// uniformly random seed. Any probability not claimed by a possible drop belongs to
+    /// `Drop::Nothing`, so the probabilities always sum to 1.
+    pub fn normalized_chances(&self, possible_drops: &DropSet) -> [f32; 6] {
+        let rates = self.expected_drops_all(possible_drops, 1);
+        let drops = self.num_drops() as f64;
+        Drop::ALL.map(|drop| (rates.get(drop) / drops) as f32)
+    }
+
+    /// Calculates the ideal number of times `drop` will be dropped per frame spent farming, when
+    /// farming `count` of this enemy at a time takes `frames_per_kill` frames.
+    ///
+    /// This is useful for comparing farming spots, since the time a kill takes depends on the
+    /// enemy and the strategy used.
+    pub fn drops_per_frame(
+        &self,
+        drop: Drop,
+        possible_drops: &DropSet,
+        count: u32,
+        frames_per_kill: u32,
+    ) -> f32 {
+        self.ideal_drops_per_farm(drop, possible_drops, count) / frames_per_kill as f32
+    }
+
+    /// Calculates ideal drops like `ideal_drops_per_farm`, but conditioned on every drop being
+    /// something other than `Drop::Nothing`.
+    ///
+    /// This answers questions like "given that an item drops, how often is it a super?". Returns
+    /// 0 if nothing but `Drop::Nothing` can be dropped.
+    pub fn conditional_drops_per_farm(
+        &self,
+        drop: Drop,
+        possible_drops: &DropSet,
+        farms: u32,
+    ) -> f32 {
+        if drop == Drop::Nothing {
             return 0.;
         }
 
-        let pooled_minor = possible_drops
-            .intersection(&DropSet::MINOR)
+        let something = possible_drops
             .iter()
-            .map(|d| self[d])
-            .sum::<u8>() as u16;
-
-        let pooled_major_complement = 0xFF
-            - possible_drops
-                .intersection(&DropSet::MAJOR)
-                .iter()
-                .map(|d| self[d])
-                .sum::<u8>() as u16;
-
-        let chance = if !drop.is_major() {
-            self[drop] as u16 * pooled_major_complement / pooled_minor
+            .filter(|&d| d != Drop::Nothing)
+            .map(|d| self.ideal_drops_per_farm(d, possible_drops, 1))
+            .sum::<f32>();
+        if something == 0. {
+            return 0.;
+        }
+
+        self.ideal_drops_per_farm(drop, possible_drops, farms) * self.num_drops() as f32 / something
+    }
+
+    /// Calculates ideal drops based purely on probabilities in the drop table, without rounding.
+    ///
+    /// Unlike `ideal_drops_per_farm`, which truncates each scaled minor drop chance to an integer
+    /// as the game does, this computes the scaled chances exactly. The game's truncation leaves a
+    /// small amount of probability unclaimed by any minor drop, so for enemies whose minor chances
+    /// don't divide evenly this returns slightly higher expectations for minor drops, and a lower
+    /// one for `Drop::Nothing` (which `ideal_drops_per_farm` credits with the unclaimed remainder).
+    pub fn exact_drops_per_farm(&self, drop: Drop, possible_drops: &DropSet, farms: u32) -> f64 {
+        if !possible_drops.contains(&drop) {
+            return 0.;
+        }
+
+        let sum = |set: DropSet| set.iter().map(|d| self[d] as f64).sum::<f64>();
+        let pooled_minor = sum(possible_drops.intersection(&DropSet::MINOR));
+        let pooled_major = sum(possible_drops.intersection(&DropSet::MAJOR));
+
+        let chance = if drop.is_major() {
+            self[drop] as f64
+        } else if pooled_minor == 0. {
+            0.
         } else {
-            self[drop] as u16
+            self[drop] as f64 * (255. - pooled_major) / pooled_minor
         };
 
-        chance as f32 / 255.
-            * (self.count.unwrap_or(1) + self.extra.as_ref().map(|_| 1).unwrap_or(0)) as f32
-            * farms as f32
+        chance / 255. * self.num_drops() as f64 * farms as f64
+    }
+
+    /// The number of drops generated each time this enemy is killed, including any extra drop
+    /// from its explosion.
+    pub fn num_drops(&self) -> u32 {
+        self.count.unwrap_or(1) + self.extra.as_ref().map(|_| 1).unwrap_or(0)
     }
 
     /// Simulates a single drop (even if this enemy drops multiple items).
+    ///
+    /// A roll whose low byte is zero is discarded and re-rolled, so a drop consumes a variable
+    /// number of RNG calls; see `zero_rerolls`.
+    ///
+    /// The chances of the possible minor drops are scaled to fill the probability space not
+    /// claimed by the possible major drops. If the minor drops' pooled chance is zero (for
+    /// instance, because every minor drop with a nonzero chance is excluded by `possible_drops`),
+    /// no minor drop is generated: the major drops keep their usual chances, and any roll not
+    /// claimed by a major drop produces `Drop::Nothing`.
     pub fn roll_one(&self, rng: &mut Rng, possible_drops: &DropSet) -> Drop {
+        self.try_roll_one(rng, possible_drops).unwrap()
+    }
+
+    /// Simulates a single drop, returning an error instead of panicking if the drop chances can't
+    /// be pooled. See `roll_one`.
+    ///
+    /// The RNG is not advanced if an error is returned.
+    pub fn try_roll_one(&self, rng: &mut Rng, possible_drops: &DropSet) -> Result<Drop, DropError> {
+        self.roll_one_with_cost(rng, possible_drops, &mut RngCost::default())
+    }
+
+    /// Simulates a single drop like `try_roll_one`, and also returns a breakdown of how the drop
+    /// was chosen.
+    pub fn roll_one_explained(
+        &self,
+        rng: &mut Rng,
+        possible_drops: &DropSet,
+    ) -> Result<(Drop, DropExplanation), DropError> {
+        let mut replay = rng.clone();
+        let mut cost = RngCost::default();
+        let drop = self.roll_one_with_cost(rng, possible_drops, &mut cost)?;
+        let rolls = replay.roll_n(cost.rolls as usize);
+
+        let (pooled_minor, pooled_major) = self.pools(possible_drops)?;
+        let pooled_major_complement = 0xFF - pooled_major;
+        let mut thresholds = Vec::new();
+        let mut acc = 0;
+        for drop in possible_drops.intersection(&DropSet::MINOR) {
+            acc += ((self[drop] as u16) * pooled_major_complement)
+                .checked_div(pooled_minor)
+                .unwrap_or(0);
+            thresholds.push((drop, acc));
+        }
+        for drop in possible_drops.intersection(&DropSet::MAJOR) {
+            acc += self[drop] as u16;
+            thresholds.push((drop, acc));
+        }
+
+        let explanation = DropExplanation {
+            random: rolls.last().unwrap() & 0xFF,
+            rolls,
+            pooled_minor,
+            pooled_major,
+            thresholds,
+        };
+        Ok((drop, explanation))
+    }
+
+    /// Simulates a single drop, adding the RNG calls it consumes to `cost`.
+    fn roll_one_with_cost(
+        &self,
+        rng: &mut Rng,
+        possible_drops: &DropSet,
+        cost: &mut RngCost,
+    ) -> Result<Drop, DropError> {
+        let (pooled_minor, pooled_major) = self.pools(possible_drops)?;
+        let pooled_major_complement = 0xFF - pooled_major;
+
         let random = loop {
+            cost.rolls += 1;
             match rng.roll() as u8 {
-                0 => continue,
+                0 => cost.zero_rerolls += 1,
                 n => break n as u16,
             }
         };
 
-        let pooled_minor = possible_drops
-            .intersection(&DropSet::MINOR)
-            .iter()
-            .map(|d| self[d])
-            .sum::<u8>() as u16;
-
-        let pooled_major_complement = 0xFF
-            - possible_drops
-                .intersection(&DropSet::MAJOR)
-                .iter()
-                .map(|d| self[d])
-                .sum::<u8>() as u16;
-
         let mut acc = 0;
         for drop in possible_drops.intersection(&DropSet::MINOR) {
-            if pooled_minor != 0 {
-                acc += (self[drop] as u16) * pooled_major_complement / pooled_minor;
+            if let Some(chance) =
+                ((self[drop] as u16) * pooled_major_complement).checked_div(pooled_minor)
+            {
+                acc += chance;
             }
             if acc >= random {
-                return drop;
+                return Ok(drop);
             }
         }
 
         for drop in possible_drops.intersection(&DropSet::MAJOR) {
             acc += self[drop] as u16;
             if acc >= random {
-                return drop;
+                return Ok(drop);
             }
         }
 
-        Drop::Nothing
+        Ok(Drop::Nothing)
+    }
+
+    /// Returns the sum of the chances of the minor drops in `possible_drops`.
+    ///
+    /// When generating a drop, the minor drop chances are scaled by this sum to fill the
+    /// probability space not claimed by the major drops.
+    pub fn pooled_minor(&self, possible_drops: &DropSet) -> u16 {
+        let minor = possible_drops.intersection(&DropSet::MINOR);
+        minor.iter().map(|d| self[d] as u16).sum()
+    }
+
+    /// Returns the sum of the chances of the major drops in `possible_drops`.
+    pub fn pooled_major(&self, possible_drops: &DropSet) -> u16 {
+        let major = possible_drops.intersection(&DropSet::MAJOR);
+        major.iter().map(|d| self[d] as u16).sum()
+    }
+
+    /// Returns the pooled chances of the possible minor and major drops.
+    fn pools(&self, possible_drops: &DropSet) -> Result<(u16, u16), DropError> {
+        let pooled_minor = self.pooled_minor(possible_drops);
+        let pooled_major = self.pooled_major(possible_drops);
+
+        if Drop::ALL.iter().all(|&drop| self[drop] == 0) {
+            Err(DropError::AllZero)
+        } else if pooled_minor > 0xFF {
+            Err(DropError::MinorOverflow(pooled_minor))
+        } else if pooled_major > 0xFF {
+            Err(DropError::MajorOverflow(pooled_major))
+        } else {
+            Ok((pooled_minor, pooled_major))
+        }
     }
 
     /// Simulates this enemy's drops.
@@ -394,37 +940,131 @@ impl DropTable {
         possible_drops: &'a DropSet,
         n: u32,
     ) -> impl Iterator<Item = Drop> + 'a {
-        let mut main_drops = 0;
-        let mut extra_drops = 0;
-        std::iter::from_fn(move || {
-            let count = self.count.unwrap_or(1) * n;
+        self.try_roll_multiple(rng, possible_drops, n).unwrap()
+    }
 
-            if main_drops < count {
-                main_drops += 1;
-                rng.roll();
-                Some(self.roll_one(rng, possible_drops))
-            } else if let Some(extra) = &self.extra {
-                if extra_drops < n {
-                    if extra_drops == 0 {
-                        for _ in 0..extra.num_explosions() {
-                            for _ in 0..extra.rng_per_explosion() * n {
-                                rng.roll();
-                            }
-
-                            for _ in 0..extra.explosion_interval() {
-                                rng.frame_advance();
-                            }
-                        }
-                    }
-
-                    extra_drops += 1;
-                    Some(self.roll_one(rng, possible_drops))
-                } else {
-                    None
+    /// Simulates the drops obtained by farming multiple of this enemy in a single frame,
+    /// returning an error instead of panicking if the drop chances can't be pooled.
+    pub fn try_roll_multiple<'a>(
+        &'a self,
+        rng: &'a mut Rng,
+        possible_drops: &'a DropSet,
+        n: u32,
+    ) -> Result<impl Iterator<Item = Drop> + 'a, DropError> {
+        self.drop_iter(rng, possible_drops, n)
+    }
+
+    /// Simulates the drops obtained by farming multiple of this enemy in a single frame, like
+    /// `roll_multiple`, and also returns the number of RNG calls the simulation consumed
+    /// (including those made by any explosion sequence).
+    pub fn roll_multiple_counted(
+        &self,
+        rng: &mut Rng,
+        possible_drops: &DropSet,
+        n: u32,
+    ) -> (Vec<Drop>, RngCost) {
+        let mut iter = self.drop_iter(rng, possible_drops, n).unwrap();
+        let drops = iter.by_ref().collect();
+        (drops, iter.cost)
+    }
+
+    fn drop_iter<'a>(
+        &'a self,
+        rng: &'a mut Rng,
+        possible_drops: &'a DropSet,
+        n: u32,
+    ) -> Result<DropIter<'a>, DropError> {
+        self.pools(possible_drops)?;
+        Ok(DropIter {
+            table: self,
+            rng,
+            possible_drops,
+            n,
+            main_drops: 0,
+            extra_drops: 0,
+            cost: RngCost::default(),
+        })
+    }
+}
+
+/// A breakdown of how `DropTable::roll_one` chose a drop, as returned by
+/// `DropTable::roll_one_explained`.
+#[derive(Clone, Debug, Serialize)]
+pub struct DropExplanation {
+    /// The values returned by each RNG call. Rolls whose low byte is zero are discarded, so only
+    /// the last one is used.
+    pub rolls: Vec<u16>,
+
+    /// The low byte of the last roll, which is compared against the thresholds.
+    pub random: u16,
+
+    /// The pooled chance of the possible minor drops.
+    pub pooled_minor: u16,
+
+    /// The pooled chance of the possible major drops.
+    pub pooled_major: u16,
+
+    /// Each possible drop in the order it is checked, with the accumulated chance up to and
+    /// including it. The first drop whose threshold is at least `random` is chosen; if there is
+    /// none, nothing drops.
+    pub thresholds: Vec<(Drop, u16)>,
+}
+
+/// The RNG calls consumed by a drop simulation.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RngCost {
+    /// The number of direct calls to `Rng::roll`.
+    pub rolls: u32,
+
+    /// The number of calls to `Rng::frame_advance`.
+    pub frame_advances: u32,
+
+    /// The number of rolls discarded because their low byte was zero (see `zero_rerolls`). These
+    /// are also counted in `rolls`.
+    pub zero_rerolls: u32,
+}
+
+/// The iterator returned by `DropTable::try_roll_multiple`.
+struct DropIter<'a> {
+    table: &'a DropTable,
+    rng: &'a mut Rng,
+    possible_drops: &'a DropSet,
+    n: u32,
+    main_drops: u32,
+    extra_drops: u32,
+    cost: RngCost,
+}
+
+impl Iterator for DropIter<'_> {
+    type Item = Drop;
+
+    fn next(&mut self) -> Option<Drop> {
+        let count = self.table.count.unwrap_or(1) * self.n;
+        let roll_one = |iter: &mut Self| {
+            let drop = iter
+                .table
+                .roll_one_with_cost(iter.rng, iter.possible_drops, &mut iter.cost);
+            Some(drop.unwrap())
+        };
+
+        if self.main_drops < count {
+            self.main_drops += 1;
+            self.rng.roll();
+            self.cost.rolls += 1;
+            roll_one(self)
+        } else if let Some(extra) = &self.table.extra {
+            if self.extra_drops < self.n {
+                if self.extra_drops == 0 {
+                    extra.simulate_with_cost(self.rng, self.n, &mut self.cost);
                 }
+
+                self.extra_drops += 1;
+                roll_one(self)
             } else {
                 None
             }
-        })
+        } else {
+            None
+        }
     }
 }
@@ -0,0 +1,39 @@
+/// Formats a seed for display, either in hexadecimal (`0x0061`) or in decimal (`97`).
+pub fn format_seed(seed: u16, hex: bool) -> String {
+    if hex {
+        format!("{seed:#06x}")
+    } else {
+        seed.to_string()
+    }
+}
+
+/// Formats a count for display, grouping its digits into thousands (`65,536`).
+pub fn format_count(count: u64) -> String {
+    let digits = count.to_string();
+    let mut result = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            result.push(',');
+        }
+        result.push(digit);
+    }
+    result
+}
+
+/// Formats `num / denom` as a percentage with a fixed width of 4 characters, using as many
+/// decimal places as fit.
+pub fn format_percentage(num: u32, denom: u32) -> String {
+    const WIDTH: usize = 4;
+    let percentage = (num as f32) / (denom as f32) * 100.;
+
+    // Compare against the rounding boundaries rather than the powers of 10, so that e.g. 9.999
+    // isn't rounded up to "10.00".
+    let digits_after_decimal = if percentage >= 99.95 {
+        0
+    } else if percentage >= 9.995 {
+        1
+    } else {
+        2
+    };
+    format!("{:>w$.a$}", percentage, w = WIDTH, a = digits_after_decimal)
+}
@@ -1,5 +1,6 @@
 pub use rng::Rng;
 
 pub mod drops;
+pub mod format;
 pub mod loop_analysis;
 pub mod rng;
@@ -1,17 +1,20 @@
-use serde::Serialize;
+use std::{ops::Index, sync::OnceLock};
 
-use crate::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{format::format_count, Rng};
 
 /// The structure of RNG loops and branches given a particular RNG configuration.
 ///
 /// Every RNG seed can be classified as part of either a loop (a cyclic set of seeds) or a branch
 /// (eventually leading into a loop).
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize)]
 pub struct Analysis {
     /// The RNG configuration under analysis.
     pub rng: Rng,
 
     /// The behavior of all possible seeds with this RNG configuration.
+    #[serde(with = "compact_seeds")]
     pub seeds: Vec<SeedInfo>,
 
     /// A list of all RNG branches.
@@ -19,37 +22,158 @@ pub struct Analysis {
 
     /// A list of all RNG seeds.
     pub loops: Vec<LoopInfo>,
+
+    /// The cached result of `predecessor_map`.
+    #[serde(skip)]
+    predecessors: OnceLock<Vec<Vec<u16>>>,
 }
 
 /// Whether a given RNG seed is a branch or a loop.
 #[derive(Clone, Copy, PartialEq, Eq, Serialize)]
 pub enum SeedInfo {
-    Branch { id: u16 },
-    Loop { id: u16 },
+    Branch {
+        id: u16,
+    },
+    Loop {
+        id: u16,
+    },
+
+    /// The seed was not examined; see `Rng::analyze_reachable`.
+    Unclassified,
 }
 
 /// A branch is a set of RNG seeds that are not themselves part of a loop,
 /// but eventually lead into one.
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize)]
 pub struct BranchInfo {
     pub seeds: Vec<u16>,
     pub loop_id: u16,
 }
 
 /// A loop is a set of RNG seeds that form a cycle.
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize)]
 pub struct LoopInfo {
     pub seeds: Vec<u16>,
+
+    /// The number of seeds that eventually lead into this loop, including the loop itself.
+    ///
+    /// Only classified seeds are counted, so for `Rng::analyze_reachable` this is just the length
+    /// of the loop plus the length of the branch leading into it.
+    pub basin_size: u32,
+}
+
+/// Serializes seed classifications compactly, since there are 65536 of them: each seed is a
+/// loop's ID, the bitwise complement of a branch's ID (so that branches are negative), or null if
+/// the seed is unclassified.
+mod compact_seeds {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    use super::SeedInfo;
+
+    pub fn serialize<S: Serializer>(seeds: &[SeedInfo], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(seeds.iter().map(|info| match *info {
+            SeedInfo::Loop { id } => Some(id as i32),
+            SeedInfo::Branch { id } => Some(!(id as i32)),
+            SeedInfo::Unclassified => None,
+        }))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<SeedInfo>, D::Error> {
+        let id = |id: i32| {
+            u16::try_from(id).map_err(|_| D::Error::custom(format!("invalid seed info {id}")))
+        };
+        Vec::<Option<i32>>::deserialize(deserializer)?
+            .into_iter()
+            .map(|info| match info {
+                Some(n) if n >= 0 => Ok(SeedInfo::Loop { id: id(n)? }),
+                Some(n) => Ok(SeedInfo::Branch { id: id(!n)? }),
+                None => Ok(SeedInfo::Unclassified),
+            })
+            .collect()
+    }
+}
+
+impl SeedInfo {
+    /// Whether the seed is part of a loop.
+    pub fn is_loop(&self) -> bool {
+        matches!(self, SeedInfo::Loop { .. })
+    }
+
+    /// Whether the seed is part of a branch.
+    pub fn is_branch(&self) -> bool {
+        matches!(self, SeedInfo::Branch { .. })
+    }
+
+    /// Returns the ID of the loop or branch the seed is part of, or `None` if the seed is
+    /// unclassified.
+    pub fn id(&self) -> Option<u16> {
+        match *self {
+            SeedInfo::Branch { id } | SeedInfo::Loop { id } => Some(id),
+            SeedInfo::Unclassified => None,
+        }
+    }
+}
+
+impl LoopInfo {
+    /// The period of the main loop reached from the reset state with vanilla RNG behavior, which
+    /// nearly all seeds eventually enter.
+    pub const MAIN_LOOP_PERIOD: usize = 2280;
+
+    /// Returns the representative first seed of this loop.
+    ///
+    /// This is the first seed of the loop encountered during analysis; for loop 0, it is where the
+    /// starting seed enters the loop.
+    pub fn start(&self) -> u16 {
+        self.seeds[0]
+    }
+
+    /// Returns the number of seeds in this loop.
+    pub fn period(&self) -> usize {
+        self.seeds.len()
+    }
+}
+
+/// Summary statistics about an `Analysis`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct AnalysisStats {
+    /// The number of loops.
+    pub loops: usize,
+
+    /// The number of branches.
+    pub branches: usize,
+
+    /// The period of the longest loop.
+    pub longest_period: usize,
+
+    /// The period of the shortest loop.
+    pub shortest_period: usize,
+
+    /// The total number of seeds that are part of a loop.
+    pub loop_seeds: usize,
+
+    /// The total number of seeds that are part of a branch.
+    pub branch_seeds: usize,
+
+    /// The length of the longest branch.
+    pub longest_branch: usize,
+
+    /// The ID of the loop with the largest basin of attraction (the most seeds that eventually
+    /// lead into it, including its own).
+    pub largest_basin: u16,
 }
 
 impl Rng {
     /// Performs loop analysis on this RNG to determine all possible loops and branches.
     pub fn analyze(&self) -> Analysis {
-        let mut seeds = [Option::<SeedInfo>::None; 0x10000];
+        let mut seeds = vec![Option::<SeedInfo>::None; 0x10000];
         let mut branches = Vec::new();
         let mut loops = Vec::new();
 
-        // Check the starting seed first, so that it gets assigned branch 0 and loop 0.
+        // Check the starting seed first, so that it gets assigned branch 0 and loop 0. Since every
+        // seed is then used as a starting point, every seed is classified no matter how the RNG is
+        // configured; and since each pass of the inner loop classifies a new seed, it terminates.
         for start in std::iter::once(self.seed).chain(0..=0xFFFFu16) {
             if seeds[start as usize].is_some() {
                 continue;
@@ -68,7 +192,7 @@ impl Rng {
             }
 
             match seeds[rng.seed as usize] {
-                None => unreachable!(),
+                None | Some(SeedInfo::Unclassified) => unreachable!(),
                 Some(SeedInfo::Loop { id }) => {
                     // We've found a new branch leading into an existing loop.
                     branches.push(BranchInfo {
@@ -107,6 +231,7 @@ impl Rng {
 
                     loops.push(LoopInfo {
                         seeds: loop_seeds.to_vec(),
+                        basin_size: 0,
                     })
                 }
                 suffix @ Some(SeedInfo::Branch { id }) => {
@@ -122,26 +247,392 @@ impl Rng {
             }
         }
 
+        for l in &mut loops {
+            l.basin_size = l.seeds.len() as u32;
+        }
+        for branch in &branches {
+            loops[branch.loop_id as usize].basin_size += branch.seeds.len() as u32;
+        }
+
         Analysis {
             rng: self.clone(),
-            seeds: seeds.into_iter().map(Option::unwrap).collect(),
+            seeds: seeds
+                .into_iter()
+                .map(|info| info.expect("every seed is used as a starting point"))
+                .collect(),
             branches,
             loops,
+            predecessors: OnceLock::new(),
         }
     }
+
+    /// Performs loop analysis on only the seeds reachable from the current seed: the branch
+    /// leading from it (if it is not part of a loop) and the loop it enters.
+    ///
+    /// This is much faster than `analyze`. All other seeds are `SeedInfo::Unclassified`.
+    pub fn analyze_reachable(&self) -> Analysis {
+        let path: Vec<u16> = self.seeds_until_loop().collect();
+        let mut rng = self.with_seed(*path.last().unwrap());
+        rng.frame_advance();
+        let loop_start = path.iter().position(|&seed| seed == rng.seed).unwrap();
+        let (branch_seeds, loop_seeds) = path.split_at(loop_start);
+
+        let mut seeds = vec![SeedInfo::Unclassified; 0x10000];
+        let mut branches = Vec::new();
+        for &seed in loop_seeds {
+            seeds[seed as usize] = SeedInfo::Loop { id: 0 };
+        }
+        if !branch_seeds.is_empty() {
+            for &seed in branch_seeds {
+                seeds[seed as usize] = SeedInfo::Branch { id: 0 };
+            }
+            branches.push(BranchInfo {
+                seeds: branch_seeds.to_vec(),
+                loop_id: 0,
+            });
+        }
+
+        Analysis {
+            rng: self.clone(),
+            seeds,
+            branches,
+            loops: vec![LoopInfo {
+                seeds: loop_seeds.to_vec(),
+                basin_size: path.len() as u32,
+            }],
+            predecessors: OnceLock::new(),
+        }
+    }
+}
+
+impl Index<u16> for Analysis {
+    type Output = SeedInfo;
+
+    /// Returns the classification of a seed.
+    fn index(&self, seed: u16) -> &SeedInfo {
+        &self.seeds[seed as usize]
+    }
+}
+
+/// The seeds whose classification differs between two analyses; see `Analysis::diff`.
+///
+/// Loop and branch IDs are assigned independently for each analysis, so only whether each seed is
+/// part of a loop or a branch is compared. Seeds that are unclassified in either analysis are
+/// ignored.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct AnalysisDiff {
+    /// The seeds that are part of a loop in the first analysis, but a branch in the second.
+    pub loop_to_branch: Vec<u16>,
+
+    /// The seeds that are part of a branch in the first analysis, but a loop in the second.
+    pub branch_to_loop: Vec<u16>,
+}
+
+impl AnalysisDiff {
+    /// Returns the number of seeds whose classification changed.
+    pub fn changed(&self) -> usize {
+        self.loop_to_branch.len() + self.branch_to_loop.len()
+    }
+
+    /// Returns up to `n` of the seeds whose classification changed, in ascending order.
+    pub fn examples(&self, n: usize) -> Vec<u16> {
+        let mut examples: Vec<u16> = self
+            .loop_to_branch
+            .iter()
+            .chain(&self.branch_to_loop)
+            .copied()
+            .collect();
+        examples.sort();
+        examples.truncate(n);
+        examples
+    }
 }
 
 impl Analysis {
+    /// Computes summary statistics about the loops and branches.
+    pub fn stats(&self) -> AnalysisStats {
+        let periods = || self.loops.iter().map(|l| l.seeds.len());
+
+        let largest_basin = (0..self.loops.len())
+            .max_by_key(|&id| self.loops[id].basin_size)
+            .unwrap_or(0) as u16;
+
+        AnalysisStats {
+            loops: self.loops.len(),
+            branches: self.branches.len(),
+            longest_period: periods().max().unwrap_or(0),
+            shortest_period: periods().min().unwrap_or(0),
+            loop_seeds: periods().sum(),
+            branch_seeds: self.branches.iter().map(|b| b.seeds.len()).sum(),
+            longest_branch: self
+                .branches
+                .iter()
+                .map(|b| b.seeds.len())
+                .max()
+                .unwrap_or(0),
+            largest_basin,
+        }
+    }
+
+    /// Compares the classification of every seed with another analysis, such as one of the same
+    /// seed with XBA enabled.
+    pub fn diff(&self, other: &Analysis) -> AnalysisDiff {
+        let mut diff = AnalysisDiff {
+            loop_to_branch: Vec::new(),
+            branch_to_loop: Vec::new(),
+        };
+        for seed in 0..=0xFFFF {
+            match (self[seed], other[seed]) {
+                (SeedInfo::Loop { .. }, SeedInfo::Branch { .. }) => diff.loop_to_branch.push(seed),
+                (SeedInfo::Branch { .. }, SeedInfo::Loop { .. }) => diff.branch_to_loop.push(seed),
+                _ => {}
+            }
+        }
+        diff
+    }
+
+    /// Returns the loop that the starting seed (`self.rng.seed`) eventually enters.
+    ///
+    /// Since the starting seed is analyzed first, this is always loop 0.
+    pub fn start_loop(&self) -> &LoopInfo {
+        let id = self
+            .loop_id(self.rng.seed)
+            .expect("the starting seed is always classified");
+        &self.loops[id as usize]
+    }
+
+    /// Returns the ID of the loop that `seed` eventually enters, or `None` if it is unclassified.
+    pub fn loop_id(&self, seed: u16) -> Option<u16> {
+        match self[seed] {
+            SeedInfo::Loop { id } => Some(id),
+            SeedInfo::Branch { id } => Some(self.branches[id as usize].loop_id),
+            SeedInfo::Unclassified => None,
+        }
+    }
+
+    /// Returns the loop that `seed` eventually enters, or `None` if it is unclassified.
+    ///
+    /// This is an exact lookup, the `LoopInfo` for `loop_id(seed)`; there is no tolerance. "Near"
+    /// means `seed` may be anywhere in the loop or in a branch leading to it, not only at the
+    /// loop's `start`.
+    pub fn find_loop_starting_near(&self, seed: u16) -> Option<&LoopInfo> {
+        self.loop_id(seed).map(|id| &self.loops[id as usize])
+    }
+
+    /// Returns whether seeds `a` and `b` eventually enter the same loop.
+    pub fn shares_loop_with(&self, a: u16, b: u16) -> bool {
+        matches!((self.loop_id(a), self.loop_id(b)), (Some(a), Some(b)) if a == b)
+    }
+
+    /// Returns the pairs of distinct loops whose seeds are byte-swaps of one another, with the
+    /// lower loop ID first.
+    ///
+    /// In XBA rooms, such loops are mirror images, so analyzing one of them says everything about
+    /// the other. Returns an empty list if XBA is disabled.
+    pub fn mirror_loops(&self) -> Vec<(usize, usize)> {
+        if !self.rng.xba {
+            return Vec::new();
+        }
+
+        let mut result = Vec::new();
+        for (id, l) in self.loops.iter().enumerate() {
+            let SeedInfo::Loop { id: mirror } = self[l.seeds[0].swap_bytes()] else {
+                continue;
+            };
+            let mirror = mirror as usize;
+            if mirror > id
+                && self.loops[mirror].seeds.len() == l.seeds.len()
+                && l.seeds
+                    .iter()
+                    .all(|&seed| self[seed.swap_bytes()] == SeedInfo::Loop { id: mirror as u16 })
+            {
+                result.push((id, mirror));
+            }
+        }
+        result
+    }
+
+    /// Returns how many frames `a` is ahead of `b`: the number of frames it takes to reach `a`
+    /// from `b`, which is between 0 and the loop's period. Returns `None` unless `a` and `b` are
+    /// both part of the same loop.
+    pub fn loop_phase_difference(&self, a: u16, b: u16) -> Option<i64> {
+        let (SeedInfo::Loop { id }, SeedInfo::Loop { id: other }) = (self[a], self[b]) else {
+            return None;
+        };
+        if id != other {
+            return None;
+        }
+
+        let seeds = &self.loops[id as usize].seeds;
+        let position = |seed| seeds.iter().position(|&s| s == seed).unwrap() as i64;
+        Some((position(a) - position(b)).rem_euclid(seeds.len() as i64))
+    }
+
+    /// Returns, for each seed, the number of seeds whose sequence eventually passes through it
+    /// (including itself).
+    ///
+    /// Every seed in a loop is visited by the loop's entire basin, so seeds in the same loop have
+    /// equal counts; a seed in a branch is visited by itself and every seed upstream of it. This
+    /// measures how often each seed is visited when starting from a uniformly random seed.
+    /// Unclassified seeds have a count of 0.
+    pub fn visit_counts(&self) -> Vec<u32> {
+        let mut counts = vec![0; 0x10000];
+        let mut in_degree = vec![0u32; 0x10000];
+        for seed in 0..=0xFFFF {
+            if self[seed].is_branch() {
+                counts[seed as usize] = 1;
+                in_degree[self.successor_of(seed) as usize] += 1;
+            }
+        }
+
+        // Propagate counts down each branch, starting from the seeds with no predecessors.
+        let mut stack: Vec<u16> = (0..=0xFFFF)
+            .filter(|&seed| self[seed].is_branch() && in_degree[seed as usize] == 0)
+            .collect();
+        while let Some(seed) = stack.pop() {
+            let next = self.successor_of(seed);
+            if self[next].is_branch() {
+                counts[next as usize] += counts[seed as usize];
+                in_degree[next as usize] -= 1;
+                if in_degree[next as usize] == 0 {
+                    stack.push(next);
+                }
+            }
+        }
+
+        for l in &self.loops {
+            for &seed in &l.seeds {
+                counts[seed as usize] = l.basin_size;
+            }
+        }
+        counts
+    }
+
+    /// Returns the seed that follows `seed` after one frame.
+    pub fn successor_of(&self, seed: u16) -> u16 {
+        let mut rng = self.rng.with_seed(seed);
+        rng.frame_advance();
+        rng.seed
+    }
+
+    /// Returns the seeds that lead directly to each seed after one frame, indexed by seed, each in
+    /// ascending order.
+    ///
+    /// This is computed (with `Rng::predecessor_map`) the first time it is needed, then cached.
+    pub fn predecessor_map(&self) -> &[Vec<u16>] {
+        self.predecessors.get_or_init(|| self.rng.predecessor_map())
+    }
+
+    /// Returns every seed that leads to `seed` after one frame, in ascending order.
+    pub fn predecessors_of(&self, seed: u16) -> Vec<u16> {
+        self.predecessor_map()[seed as usize].clone()
+    }
+
+    /// Returns every seed that reaches `target` within `within` frames, ordered by the number of
+    /// frames it takes to reach `target`. `target` itself is always included, as it reaches itself
+    /// in 0 frames.
+    pub fn seeds_reaching(&self, target: u16, within: u64) -> Vec<u16> {
+        let predecessors = self.predecessor_map();
+
+        let mut result = vec![target];
+        let mut frontier = 0;
+        for _ in 0..within {
+            let end = result.len();
+            for i in frontier..end {
+                for &seed in &predecessors[result[i] as usize] {
+                    // A loop leads back to the target; don't visit it again.
+                    if seed != target {
+                        result.push(seed);
+                    }
+                }
+            }
+            if end == result.len() {
+                break;
+            }
+            frontier = end;
+        }
+        result
+    }
+
+    /// Returns the seed with the highest `score` among the seeds reachable from `from` within
+    /// `within` frames (including `from` itself). Ties go to the seed reached soonest.
+    ///
+    /// Seeds scored as NaN are ignored, so this returns `None` only if every reachable seed is.
+    pub fn best_reachable_seed(
+        &self,
+        from: u16,
+        within: u64,
+        score: impl Fn(u16) -> f32,
+    ) -> Option<u16> {
+        let mut best: Option<(u16, f32)> = None;
+        for (seed, _) in self.rng.with_seed(from).seeds_until_loop().zip(0..=within) {
+            let score = score(seed);
+            if !score.is_nan() && best.is_none_or(|(_, best)| score > best) {
+                best = Some((seed, score));
+            }
+        }
+        best.map(|(seed, _)| seed)
+    }
+
+    /// Prints the loops and branches to standard output.
     pub fn print(&self) {
+        self.print_with_color(false)
+    }
+
+    /// Prints the loops and branches to standard output as GitHub-flavored Markdown tables.
+    pub fn print_markdown(&self) {
+        println!(
+            "Loop analysis for seed `{:#06x}` (XBA: {}, calls per frame: {})",
+            self.rng.seed, self.rng.xba, self.rng.calls_per_frame
+        );
+        println!();
+        println!("| Loop | Period | Basin | Start |");
+        println!("|-----:|-------:|------:|------:|");
+        for (id, l) in self.loops.iter().enumerate() {
+            println!(
+                "| {id} | {} | {} | `{:#06x}` |",
+                l.seeds.len(),
+                format_count(l.basin_size as u64),
+                l.seeds[0]
+            );
+        }
+        println!();
+        println!("| Branch | Length | Loop |");
+        println!("|-------:|-------:|-----:|");
+        for (i, branch) in self.branches.iter().enumerate() {
+            println!(
+                "| {i} | {} | {} |",
+                format_count(branch.seeds.len() as u64),
+                branch.loop_id
+            );
+        }
+    }
+
+    /// Prints the loops and branches to standard output, optionally using ANSI escape sequences
+    /// to give each loop a distinct color and dim the branches.
+    pub fn print_with_color(&self, color: bool) {
+        /// The ANSI color codes assigned to loops, in order.
+        const LOOP_COLORS: [u8; 6] = [32, 33, 34, 35, 36, 31];
+        let escape = |code: u8| {
+            if color {
+                format!("\x1b[{code}m")
+            } else {
+                String::new()
+            }
+        };
+        let reset = escape(0);
+
         println!("Loop analysis for {:#?}", self.rng);
         println!();
         for (id, l) in self.loops.iter().enumerate() {
-            let start = l.seeds[0];
-            let period = l.seeds.len();
+            let start = l.start();
+            let period = l.period();
+            let basin = format_count(l.basin_size as u64);
+            print!("{}", escape(LOOP_COLORS[id % LOOP_COLORS.len()]));
             if period > 100 {
-                println!("Loop {id} (period {period}) at {start:#06x}");
+                println!("Loop {id} (period {period}, basin {basin}) at {start:#06x}{reset}");
             } else {
-                println!("Loop {id} (period {period}):");
+                println!("Loop {id} (period {period}, basin {basin}):");
 
                 const PER_LINE: usize = 10;
                 for (i, seed) in l.seeds.iter().enumerate() {
@@ -149,7 +640,9 @@ impl Analysis {
                         print!("    ");
                     }
                     print!("{:#06x}", seed);
-                    if i == period - 1 || (i + 1) % PER_LINE == 0 {
+                    if i == period - 1 {
+                        println!("{reset}");
+                    } else if (i + 1) % PER_LINE == 0 {
                         println!();
                     } else {
                         print!(", ");
@@ -159,13 +652,71 @@ impl Analysis {
         }
         println!();
         println!("Branches: {}", self.branches.len());
+        let dim = escape(2);
         for (i, branch) in self.branches.iter().enumerate() {
             let pad = self.branches.len().ilog10() as usize + 1;
             println!(
-                "    {i:pad$}: length {:5} -> loop {}",
-                branch.seeds.len(),
+                "{dim}    {i:pad$}: length {:>6} -> loop {}{reset}",
+                format_count(branch.seeds.len() as u64),
                 branch.loop_id
             );
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds an analysis containing only the given loops, with every other seed unclassified.
+    fn analysis_with_loops(rng: Rng, loops: &[&[u16]]) -> Analysis {
+        let mut seeds = vec![SeedInfo::Unclassified; 0x10000];
+        for (id, l) in loops.iter().enumerate() {
+            for &seed in l.iter() {
+                seeds[seed as usize] = SeedInfo::Loop { id: id as u16 };
+            }
+        }
+        Analysis {
+            rng,
+            seeds,
+            branches: Vec::new(),
+            loops: loops
+                .iter()
+                .map(|l| LoopInfo {
+                    seeds: l.to_vec(),
+                    basin_size: l.len() as u32,
+                })
+                .collect(),
+            predecessors: OnceLock::new(),
+        }
+    }
+
+    #[test]
+    fn mirror_loops() {
+        let loops: &[&[u16]] = &[
+            // Byte-swaps to itself.
+            &[0x0101],
+            &[0x1234, 0x5678],
+            // Also byte-swaps into itself, rather than into another loop.
+            &[0xab12, 0x12ab],
+            &[0x3412, 0x7856],
+        ];
+        let analysis = analysis_with_loops(Rng::POLYP, loops);
+        let mirrors = analysis.mirror_loops();
+        assert_eq!(mirrors, [(1, 3)]);
+        assert!(mirrors.iter().all(|&(a, b)| a != b));
+
+        // Without XBA, there are no mirror images.
+        let analysis = analysis_with_loops(Rng::RESET, loops);
+        assert_eq!(analysis.mirror_loops(), []);
+
+        // The swapped loop must contain exactly the swapped seeds.
+        for loops in [
+            &[&[0x1234, 0x5678][..], &[0x3412, 0x7856, 0x0001]],
+            &[&[0x1234, 0x5678][..], &[0x3412, 0x0001]],
+        ] {
+            let analysis = analysis_with_loops(Rng::POLYP, loops);
+            assert_eq!(analysis.mirror_loops(), []);
+        }
+    }
+}
@@ -1,9 +1,23 @@
-use ::smrng::drops::{analysis::DropAnalysis, Drop, DropSet};
+use ::smrng::drops::{
+    analysis::{DropAnalysis, DropRates},
+    Drop, DropError, DropSet,
+};
+use ::smrng::format::{format_count, format_percentage, format_seed};
+use ::smrng::loop_analysis::{AnalysisStats, LoopInfo, SeedInfo};
+use ::smrng::rng::{parse_range, parse_seed, parse_seed_range};
 use ::smrng::*;
 use serde::Serialize;
 
-use clap::{builder::BoolishValueParser, Parser, Subcommand};
-use std::{collections::HashMap, num::ParseIntError, process::exit};
+use clap::{
+    builder::BoolishValueParser, ColorChoice, CommandFactory, Parser, Subcommand, ValueEnum,
+};
+use clap_complete::Shell;
+use std::{
+    cmp::Reverse,
+    io::{self, BufWriter, IsTerminal, Write},
+    ops::RangeInclusive,
+    process::exit,
+};
 
 #[derive(Parser, Debug)]
 struct Args {
@@ -17,50 +31,168 @@ struct Args {
 
     /// The initial seed value. Can be a number, or 'reset', 'beetom', 'sidehopper', or 'polyp'.
     /// Defaults to 'reset'.
-    #[arg(short = 'i', long, value_parser = parse_seed, global = true)]
+    #[arg(short = 'i', long, global = true)]
     seed: Option<Rng>,
 
     /// Output in JSON format.
     #[arg(short, long, global = true)]
     json: bool,
 
+    /// Output in JSON Lines format, with one JSON value per line; implies `--json`.
+    ///
+    /// Lists such as the seeds from `dump` and the rows of a histogram are written one item per
+    /// line. Other output is written as a single line.
+    #[arg(long, global = true)]
+    jsonl: bool,
+
+    /// Output tables in GitHub-flavored Markdown format.
+    #[arg(long, global = true, conflicts_with_all = ["json", "jsonl"])]
+    markdown: bool,
+
+    /// Report progress to standard error during long analyses.
+    ///
+    /// Enabled by default when standard error is a terminal. Never shown with `--json`, or when
+    /// the histogram is computed in parallel.
+    #[arg(long, global = true)]
+    progress: bool,
+
+    /// Whether to use colors in human-readable output.
+    #[arg(long, value_enum, default_value_t = ColorChoice::Auto, global = true)]
+    color: ColorChoice,
+
     #[command(subcommand)]
     command: Command,
 }
 
-fn parse_seed(seed: &str) -> Result<Rng, ParseIntError> {
-    match seed.to_lowercase().as_str() {
-        "reset" => Ok(Rng::RESET),
-        s if s.starts_with("power") => Ok(Rng::RESET),
-
-        "beetom" => Ok(Rng::BEETOM),
-        "sidehopper" | "hopper" => Ok(Rng::SIDEHOPPER),
-        "polyp" => Ok(Rng::POLYP),
-
-        n if n.starts_with("0x") => Ok(Rng {
-            seed: u16::from_str_radix(&n[2..], 16)?,
-            ..Rng::RESET
-        }),
-        n => Ok(Rng {
-            seed: n.parse()?,
-            ..Rng::RESET
-        }),
+/// Writes a sequence of seeds as a JSON array, without collecting them first.
+///
+/// The output is identical to serializing a `Vec<u16>` with `serde_json::to_writer`.
+fn write_json_seeds(out: impl Write, seeds: impl IntoIterator<Item = u16>) -> io::Result<()> {
+    let mut out = BufWriter::new(out);
+    write!(out, "[")?;
+    for (i, seed) in seeds.into_iter().enumerate() {
+        if i != 0 {
+            write!(out, ",")?;
+        }
+        write!(out, "{seed}")?;
+    }
+    write!(out, "]")?;
+    out.flush()
+}
+
+/// Writes each item as JSON on its own line.
+fn write_json_lines<T: Serialize>(items: impl IntoIterator<Item = T>) -> io::Result<()> {
+    let mut out = BufWriter::new(std::io::stdout().lock());
+    for item in items {
+        serde_json::to_writer(&mut out, &item)?;
+        writeln!(out)?;
     }
+    out.flush()
+}
+
+/// Writes a JSON document that is small enough to be worth reading, pretty-printed unless JSON
+/// Lines output was requested.
+fn write_json_document(args: &Args, value: &impl Serialize) {
+    if args.jsonl {
+        write_json_lines([value]).unwrap();
+    } else {
+        serde_json::to_writer_pretty(std::io::stdout(), value).unwrap();
+    }
+}
+
+fn exit_drop_error<T>(e: DropError) -> T {
+    eprintln!("Invalid drop table: {e}");
+    exit(2)
+}
+
+/// Parses a stop on a farming route, such as `geega:2`, into an enemy name and a kill count. The
+/// count defaults to 1 if omitted.
+fn parse_route_stop(s: &str) -> Result<(String, u32), String> {
+    match s.split_once(':') {
+        Some((enemy, count)) => {
+            let count = count
+                .parse()
+                .map_err(|e| format!("invalid count '{count}': {e}"))?;
+            Ok((enemy.to_string(), count))
+        }
+        None => Ok((s.to_string(), 1)),
+    }
+}
+
+/// Parses a range of RNG calls per frame, such as `1..4` or `1..=3`.
+fn parse_calls_range(s: &str) -> Result<RangeInclusive<usize>, String> {
+    let range = parse_range(s, |bound| {
+        bound
+            .parse::<usize>()
+            .map_err(|e| format!("invalid call count '{bound}': {e}"))
+    })?;
+
+    if *range.start() == 0 {
+        return Err("the RNG must be called at least once per frame".to_string());
+    }
+    Ok(range)
 }
 
 impl Args {
+    fn color(&self) -> bool {
+        match self.color {
+            ColorChoice::Auto => std::io::stdout().is_terminal(),
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        }
+    }
+
+    fn progress(&self, total: usize) -> Progress {
+        Progress {
+            enabled: !self.json && (self.progress || std::io::stderr().is_terminal()),
+            total,
+            done: 0,
+        }
+    }
+
     fn rng(&self) -> Rng {
-        let mut rng = self.seed.clone().unwrap_or(Rng::RESET);
-        rng.calls_per_frame = self.calls_per_frame;
-        rng.xba = self.xba.unwrap_or(rng.xba);
-        rng
+        let rng = self.seed.clone().unwrap_or(Rng::RESET);
+        let xba = self.xba.unwrap_or(rng.xba);
+        rng.with_xba(xba).with_calls_per_frame(self.calls_per_frame)
+    }
+}
+
+/// Reports the percentage of seeds processed to standard error.
+struct Progress {
+    enabled: bool,
+    total: usize,
+    done: usize,
+}
+
+impl Progress {
+    /// How many seeds to process between updates.
+    const INTERVAL: usize = 4096;
+
+    /// Records that a seed has been processed.
+    fn tick(&mut self) {
+        self.done += 1;
+        if self.enabled && self.done.is_multiple_of(Self::INTERVAL) {
+            eprint!("\rProcessing seeds: {}%", self.done * 100 / self.total);
+        }
+    }
+
+    /// Clears the progress indicator, if it was shown.
+    fn finish(&self) {
+        if self.enabled && self.done >= Self::INTERVAL {
+            eprint!("\r\x1b[K");
+        }
     }
 }
 
 #[derive(Subcommand, Debug)]
 enum Command {
     /// Print information about RNG loops and branches.
-    Loops,
+    Loops {
+        /// Analyze every number of RNG calls per frame in a range such as `1..4` or `1..=3`,
+        /// printing a summary of the loops for each, instead of a single value.
+        #[arg(long, value_name = "MIN..MAX", value_parser = parse_calls_range)]
+        calls_range: Option<RangeInclusive<usize>>,
+    },
 
     /// Print generated random numbers to standard output.
     Dump {
@@ -79,13 +211,109 @@ enum Command {
         #[arg(short, long, conflicts_with = "loop")]
         branch: Option<usize>,
 
+        /// Dump the seeds in a range such as `0x2000..0x2100` or `0x2000..=0x20ff`.
+        #[arg(long, value_parser = parse_seed_range, conflicts_with_all = ["loop", "branch"])]
+        seed_range: Option<RangeInclusive<u16>>,
+
+        /// Dump the seeds leading up to the initial seed, in reverse order.
+        ///
+        /// If a seed has multiple predecessors, the numerically smallest one is followed.
+        #[arg(short, long, conflicts_with_all = ["loop", "branch", "seed_range"])]
+        reverse: bool,
+
+        /// Dump at most <FRAMES> values.
+        #[arg(short, long)]
+        frames: Option<usize>,
+
+        /// Output numbers in hexadecimal.
+        #[arg(long, conflicts_with = "json")]
+        hex: bool,
+    },
+
+    /// Print the seeds between two seeds, inclusive.
+    Between {
+        /// The first seed.
+        #[arg(value_parser = parse_seed)]
+        from: u16,
+
+        /// The last seed.
+        #[arg(value_parser = parse_seed)]
+        to: u16,
+
+        /// Output numbers in hexadecimal.
+        #[arg(long, conflicts_with = "json")]
+        hex: bool,
+    },
+
+    /// Print the seed that follows each seed after one frame.
+    ///
+    /// With `--json`, this outputs an array of all 65536 successors, indexed by seed. Otherwise,
+    /// only the edges where each branch joins another branch or a loop are printed.
+    Graph {
         /// Output numbers in hexadecimal.
         #[arg(long, conflicts_with = "json")]
         hex: bool,
     },
 
+    /// Print the loop or branch a seed belongs to, and which named states share its loop.
+    Classify {
+        /// The seed to classify.
+        #[arg(value_name = "SEED", value_parser = parse_seed)]
+        target: u16,
+
+        /// Output seeds in hexadecimal.
+        #[arg(long, conflicts_with = "json")]
+        hex: bool,
+    },
+
+    /// Print how many frames a seed takes to enter a loop, and the period of that loop.
+    ///
+    /// This is much faster than `loops` when only one seed is of interest.
+    Period {
+        /// The seed to examine. Defaults to the seed given by `--seed`.
+        #[arg(value_name = "SEED", value_parser = parse_seed)]
+        target: Option<u16>,
+    },
+
+    /// Print the drop table for an enemy.
+    Table {
+        /// The enemy name.
+        enemy: String,
+    },
+
+    /// Analyze drops from farming several enemies in order, sharing the same RNG state.
+    ///
+    /// Unless `--seed` is given, loop 0 of the reset seed is assumed (the 2280 loop).
+    Route {
+        /// The enemies to farm, in order, each with the number killed at once, such as
+        /// `geega:2 ripper:1`. The count defaults to 1.
+        #[arg(value_name = "ENEMY:COUNT", value_parser = parse_route_stop, required = true)]
+        stops: Vec<(String, u32)>,
+
+        /// Consider all 65536 RNG seeds, rather than just descendents of a given seed.
+        #[arg(short, long)]
+        all_seeds: bool,
+
+        /// A comma-separated list of the drops that are possible.
+        ///
+        /// Accepts drop names such as 'small_energy' or 'pb', as well as 'energy', 'ammo',
+        /// 'minor', 'major', and 'all'.
+        #[arg(long = "drops", value_name = "SET")]
+        possible_drops: Option<DropSet>,
+    },
+
+    /// Print a shell completion script to standard output.
+    Completions {
+        /// The shell to generate completions for.
+        shell: Shell,
+    },
+
     /// Print drop chances for an enemy
+    #[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
     Drops {
+        #[command(subcommand)]
+        action: Option<DropsCommand>,
+
         /// How many of the enemy are killed with a single shot.
         #[arg(short, long, default_value = "1")]
         count: u32,
@@ -102,6 +330,154 @@ enum Command {
         #[arg(long, conflicts_with = "uncorrelated", conflicts_with = "ideal")]
         histogram: bool,
 
+        /// Output the chance of getting at least one of the given drop, such as 'super' or 'pb'.
+        #[arg(
+            long,
+            conflicts_with_all = ["uncorrelated", "ideal", "histogram"]
+        )]
+        at_least_one: Option<Drop>,
+
+        /// Output the drops produced by each seed individually.
+        #[arg(
+            long,
+            conflicts_with_all = ["uncorrelated", "ideal", "histogram", "at_least_one"]
+        )]
+        per_seed: bool,
+
+        /// Output the chance that farming the enemy produces no drops at all.
+        #[arg(
+            long,
+            conflicts_with_all = ["uncorrelated", "ideal", "histogram", "at_least_one", "per_seed"]
+        )]
+        dud_chance: bool,
+
+        /// Explain how the first drop is chosen when killing the enemy at the given seed.
+        #[arg(
+            long,
+            conflicts_with_all = [
+                "uncorrelated", "ideal", "histogram", "at_least_one", "per_seed", "dud_chance",
+                "loop", "branch", "all_seeds",
+            ]
+        )]
+        explain: bool,
+
+        /// Weight each seed by how often it is visited when starting from a random seed, rather
+        /// than treating every seed equally.
+        #[arg(
+            long,
+            conflicts_with_all = [
+                "uncorrelated", "ideal", "histogram", "at_least_one", "per_seed", "dud_chance",
+                "explain",
+            ]
+        )]
+        weighted: bool,
+
+        /// Output drops per frame instead of drops per kill, given the number of frames each
+        /// kill takes.
+        #[arg(
+            long,
+            value_parser = clap::value_parser!(u32).range(1..),
+            conflicts_with_all = ["histogram", "at_least_one", "per_seed", "dud_chance", "explain"]
+        )]
+        frames_per_kill: Option<u32>,
+
+        /// Analyze every count from 1 to <MAX>, printing the mean drops for each, instead of a
+        /// single count.
+        #[arg(
+            long,
+            value_name = "MAX",
+            value_parser = clap::value_parser!(u32).range(1..),
+            conflicts_with_all = [
+                "count", "ideal", "histogram", "at_least_one", "per_seed", "dud_chance",
+                "explain", "weighted", "frames_per_kill",
+            ]
+        )]
+        count_range: Option<u32>,
+
+        /// Compare against another enemy, printing the mean drops of both over the same seeds
+        /// along with the difference.
+        #[arg(
+            long,
+            value_name = "ENEMY",
+            conflicts_with_all = [
+                "uncorrelated", "ideal", "histogram", "at_least_one", "per_seed", "dud_chance",
+                "explain", "weighted", "frames_per_kill", "count_range",
+            ]
+        )]
+        compare: Option<String>,
+
+        /// Analyze every number of RNG calls per frame in a range such as `1..4` or `1..=3`,
+        /// printing the mean drops for each, instead of a single value.
+        ///
+        /// The seeds are chosen separately for each value, so the default loop is the loop the
+        /// starting seed falls into with that many calls per frame.
+        #[arg(
+            long,
+            value_name = "MIN..MAX",
+            value_parser = parse_calls_range,
+            conflicts_with_all = [
+                "ideal", "histogram", "at_least_one", "per_seed", "dud_chance", "explain",
+                "weighted", "frames_per_kill", "count_range", "compare",
+            ]
+        )]
+        calls_range: Option<RangeInclusive<usize>>,
+
+        /// Find the seed within <FRAMES> frames of the starting seed that produces the most drops,
+        /// such as the best seed to manipulate to before farming.
+        #[arg(
+            long,
+            value_name = "FRAMES",
+            conflicts_with_all = [
+                "uncorrelated", "ideal", "histogram", "at_least_one", "per_seed", "dud_chance",
+                "explain", "weighted", "frames_per_kill", "count_range", "compare", "calls_range",
+                "sample", "continuous", "loop", "branch", "all_seeds", "seed_range",
+            ]
+        )]
+        best_reachable: Option<u64>,
+
+        /// Output the chance of each drop (including nothing) per drop generated, instead of the
+        /// mean drops per farm.
+        ///
+        /// For enemies that drop a single item, this is the chance of each drop per kill.
+        #[arg(
+            long,
+            conflicts_with_all = [
+                "ideal", "histogram", "at_least_one", "per_seed", "dud_chance", "explain",
+                "weighted", "frames_per_kill", "count_range", "compare", "calls_range",
+                "best_reachable",
+            ]
+        )]
+        probabilities: bool,
+
+        /// Analyze only a random sample of <N> of the chosen seeds, trading accuracy for speed.
+        #[arg(
+            long,
+            value_name = "N",
+            value_parser = clap::value_parser!(u32).range(1..),
+            conflicts_with_all = ["explain", "continuous"]
+        )]
+        sample: Option<u32>,
+
+        /// The seed for choosing the random sample of seeds, so that a sample can be reproduced.
+        #[arg(long, value_name = "SEED", default_value_t = 0, requires = "sample")]
+        rng_sample_seed: u64,
+
+        /// Simulate uninterrupted farming, carrying the RNG state from one kill to the next.
+        ///
+        /// By default, the RNG is reset to each seed in turn before simulating a kill, so every
+        /// seed is sampled once. With `--continuous`, farming starts at the first seed and kills
+        /// the enemy once per seed, one frame apart, without undoing the RNG calls made by the
+        /// previous kill's drops; this samples the seeds the game actually visits while farming.
+        #[arg(
+            long,
+            alias = "no-correlation-reset",
+            conflicts_with_all = [
+                "uncorrelated", "ideal", "histogram", "at_least_one", "per_seed", "dud_chance",
+                "explain", "weighted",
+            ]
+        )]
+        continuous: bool,
+
         /// Only consider RNG seeds that are part of a loop.
         /// You can optionally specify a loop ID as returned by `rng loops`.
         ///
@@ -125,6 +501,25 @@ enum Command {
         #[arg(short, long, conflicts_with = "branch", conflicts_with = "loop")]
         all_seeds: bool,
 
+        /// Only consider the RNG seeds in a range such as `0x2000..0x2100` or `0x2000..=0x20ff`.
+        #[arg(
+            long,
+            value_parser = parse_seed_range,
+            conflicts_with_all = ["loop", "branch", "all_seeds"]
+        )]
+        seed_range: Option<RangeInclusive<u16>>,
+
+        /// A comma-separated list of the drops that are possible, overriding the `--full-*` options.
+        ///
+        /// Accepts drop names such as 'small_energy' or 'pb', as well as 'energy', 'ammo',
+        /// 'minor', 'major', and 'all'.
+        #[arg(
+            long = "drops",
+            value_name = "SET",
+            conflicts_with_all = ["full_energy", "full_missiles", "full_supers", "full_pbs"]
+        )]
+        possible_drops: Option<DropSet>,
+
         /// The player is full on energy.
         #[arg(short = 'e')]
         full_energy: bool,
@@ -141,6 +536,18 @@ enum Command {
         #[arg(short = 'p', long)]
         full_pbs: bool,
 
+        /// [histogram mode] The field to sort outcomes by, in descending order.
+        #[arg(long, value_enum, default_value_t = HistogramSort::Count, requires = "histogram")]
+        sort: HistogramSort,
+
+        /// [histogram mode] Only include the <TOP> most common outcomes in the output.
+        #[arg(long, requires = "histogram")]
+        top: Option<usize>,
+
+        /// [histogram mode] Only include outcomes produced by at least <MIN_SEEDS> seeds.
+        #[arg(long, requires = "histogram")]
+        min_seeds: Option<u32>,
+
         /// [histogram mode] Only include energy drops in the output.
         #[arg(short = 'E', long, requires = "histogram")]
         filter_energy: bool,
@@ -158,73 +565,474 @@ enum Command {
         filter_pbs: bool,
 
         /// The enemy name.
-        enemy: String,
+        #[arg(required = true)]
+        enemy: Option<String>,
     },
 }
 
+/// Subcommands of `drops` that don't analyze a single enemy.
+#[derive(Subcommand, Debug)]
+enum DropsCommand {
+    /// Check the built-in drop tables for enemies whose drop chances don't sum to 255.
+    Audit,
+}
+
+/// A field to sort histogram entries by.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum HistogramSort {
+    /// The number of seeds producing the outcome.
+    Count,
+    /// The number of missiles.
+    Missile,
+    /// The number of super missiles.
+    Super,
+    /// The number of power bombs.
+    Pb,
+    /// The number of small and big energy drops combined.
+    Energy,
+}
+
+impl HistogramSort {
+    fn key(&self, entry: &DropAnalysis) -> u32 {
+        match self {
+            HistogramSort::Count => entry.seeds,
+            HistogramSort::Missile => entry.missile,
+            HistogramSort::Super => entry.super_missile,
+            HistogramSort::Pb => entry.power_bomb,
+            HistogramSort::Energy => entry.small_energy + entry.big_energy,
+        }
+    }
+}
+
 fn main() {
-    let args = Args::parse();
+    let mut args = Args::parse();
+    args.json |= args.jsonl;
     match args.command {
-        Command::Loops => {
+        Command::Loops {
+            calls_range: Some(ref range),
+        } => {
+            #[derive(Serialize)]
+            struct Entry {
+                calls_per_frame: usize,
+                /// The seed at which the starting seed enters its loop.
+                start_loop_entry: u16,
+                start_loop_period: usize,
+                start_loop_basin: u32,
+                stats: AnalysisStats,
+            }
+
+            let entries: Vec<Entry> = range
+                .clone()
+                .map(|calls_per_frame| {
+                    let analysis = args.rng().with_calls_per_frame(calls_per_frame).analyze();
+                    let start = analysis.start_loop();
+                    Entry {
+                        calls_per_frame,
+                        start_loop_entry: start.start(),
+                        start_loop_period: start.period(),
+                        start_loop_basin: start.basin_size,
+                        stats: analysis.stats(),
+                    }
+                })
+                .collect();
+
+            if args.jsonl {
+                write_json_lines(entries).unwrap();
+            } else if args.json {
+                write_json_document(&args, &entries);
+            } else if args.markdown {
+                println!("| Calls | Loops | Branches | Start entry | Start period | Start basin |");
+                println!("|------:|------:|---------:|------------:|-------------:|------------:|");
+                for entry in entries {
+                    println!(
+                        "| {} | {} | {} | {:#06x} | {} | {} |",
+                        entry.calls_per_frame,
+                        entry.stats.loops,
+                        entry.stats.branches,
+                        entry.start_loop_entry,
+                        format_count(entry.start_loop_period as u64),
+                        format_count(entry.start_loop_basin as u64),
+                    );
+                }
+            } else {
+                println!("Calls| Loops|Branches| Entry| Period|  Basin");
+                println!("-----+------+--------+------+-------+-------");
+                for entry in entries {
+                    println!(
+                        "{:>5}|{:>6}|{:>8}|{:#06x}|{:>7}|{:>7}",
+                        entry.calls_per_frame,
+                        entry.stats.loops,
+                        entry.stats.branches,
+                        entry.start_loop_entry,
+                        format_count(entry.start_loop_period as u64),
+                        format_count(entry.start_loop_basin as u64),
+                    );
+                }
+            }
+        }
+        Command::Loops { calls_range: None } => {
             let analysis = args.rng().analyze();
             if args.json {
                 serde_json::to_writer(std::io::stdout(), &analysis).unwrap();
             } else {
-                analysis.print();
+                if args.markdown {
+                    analysis.print_markdown();
+                } else {
+                    analysis.print_with_color(args.color());
+                }
             }
         }
         Command::Dump {
             loop_id,
             branch,
+            ref seed_range,
+            reverse,
+            frames,
             hex,
         } => {
-            let mut output = Vec::new();
-
-            if let Some(loop_id) = loop_id {
-                let analysis = args.rng().analyze();
-                let Some(l) = analysis.loops.get(loop_id) else {
+            let output: Box<dyn Iterator<Item = u16>> = if let Some(range) = seed_range {
+                Box::new(range.clone())
+            } else if let Some(loop_id) = loop_id {
+                let mut analysis = args.rng().analyze();
+                let Some(l) = analysis.loops.get_mut(loop_id) else {
                     eprintln!("Loop index out of range 0..={}", analysis.loops.len());
                     exit(2);
                 };
-                output = l.seeds.to_vec();
+                Box::new(std::mem::take(&mut l.seeds).into_iter())
             } else if let Some(branch_id) = branch {
-                let analysis = args.rng().analyze();
-                let Some(b) = analysis.branches.get(branch_id) else {
+                let mut analysis = args.rng().analyze();
+                let Some(b) = analysis.branches.get_mut(branch_id) else {
                     eprintln!("Branch index out of range 0..={}", analysis.branches.len());
                     exit(2);
                 };
-                output = b.seeds.to_vec();
+                Box::new(std::mem::take(&mut b.seeds).into_iter())
+            } else if reverse {
+                let predecessors = args.rng().predecessor_map();
+                let mut seen = vec![false; 0x10000];
+                let mut seed = Some(args.rng().seed);
+
+                Box::new(std::iter::from_fn(move || {
+                    let s = seed.filter(|&s| !seen[s as usize])?;
+                    seen[s as usize] = true;
+                    // Predecessors are listed in ascending order.
+                    seed = predecessors[s as usize].first().copied();
+                    Some(s)
+                }))
             } else {
                 let mut seen = vec![false; 0x10000];
                 let mut rng = args.rng();
 
-                while !seen[rng.seed as usize] {
-                    output.push(rng.seed);
-                    seen[rng.seed as usize] = true;
+                Box::new(std::iter::from_fn(move || {
+                    let s = rng.seed;
+                    if seen[s as usize] {
+                        return None;
+                    }
+                    seen[s as usize] = true;
                     rng.frame_advance();
+                    Some(s)
+                }))
+            };
+
+            let output = output.take(frames.unwrap_or(usize::MAX));
+
+            if args.jsonl {
+                write_json_lines(output).unwrap();
+            } else if args.json {
+                write_json_seeds(std::io::stdout().lock(), output).unwrap();
+            } else {
+                for seed in output {
+                    println!("{}", format_seed(seed, hex));
                 }
             }
+        }
+        Command::Period { target } => {
+            let rng = args.rng();
+            let rng = match target {
+                Some(seed) => rng.with_seed(seed),
+                None => rng,
+            };
+            let (tail, period) = rng.cycle_of(rng.seed);
+
+            if args.json {
+                #[derive(Serialize)]
+                struct Output {
+                    seed: u16,
+                    tail: u64,
+                    period: u64,
+                    in_loop: bool,
+                }
+                let output = Output {
+                    seed: rng.seed,
+                    tail,
+                    period,
+                    in_loop: tail == 0,
+                };
+                serde_json::to_writer(std::io::stdout(), &output).unwrap();
+            } else if tail == 0 {
+                println!(
+                    "Seed {:#06x} is part of a loop of period {period}",
+                    rng.seed
+                );
+            } else {
+                println!(
+                    "Seed {:#06x} enters a loop of period {period} after {tail} frames",
+                    rng.seed
+                );
+            }
+        }
+        Command::Table { ref enemy } => {
+            let Some(table) = drops::lookup_enemy(enemy) else {
+                eprintln!("Unknown enemy {enemy}");
+                exit(2)
+            };
+
+            if args.json {
+                serde_json::to_writer(std::io::stdout(), table).unwrap();
+            } else {
+                println!("Resource | Chance");
+                println!("---------+-------");
+                for (name, drop) in [
+                    ("Nothing", Drop::Nothing),
+                    ("Small E", Drop::SmallEnergy),
+                    ("Big E", Drop::BigEnergy),
+                    ("Missile", Drop::Missile),
+                    ("Super", Drop::SuperMissile),
+                    ("PB", Drop::PowerBomb),
+                ] {
+                    println!("{name:>8} | {:>6}", table[drop]);
+                }
+                println!();
+                println!("Drops per kill: {}", table.count.unwrap_or(1));
+                match &table.extra {
+                    Some(extra) => println!(
+                        "Explosion drop: {} explosions, {} RNG calls each, {} frames apart",
+                        extra.num_explosions(),
+                        extra.rng_per_explosion(),
+                        extra.explosion_interval()
+                    ),
+                    None => println!("Explosion drop: none"),
+                }
+            }
+        }
+        Command::Route {
+            ref stops,
+            all_seeds,
+            ref possible_drops,
+        } => {
+            let tables: Vec<_> = stops
+                .iter()
+                .map(|(enemy, count)| match drops::lookup_enemy(enemy) {
+                    Some(table) => (table, *count),
+                    None => {
+                        eprintln!("Unknown enemy {enemy}");
+                        exit(2)
+                    }
+                })
+                .collect();
+
+            let rng = args.rng();
+            let seeds: Vec<u16> = if all_seeds {
+                (0..=u16::MAX).collect()
+            } else if args.seed.is_some() {
+                rng.seeds_until_loop().collect()
+            } else {
+                let mut analysis = rng.analyze();
+                std::mem::take(&mut analysis.loops[0].seeds)
+            };
+
+            let analysis = drops::analysis::analyze_route(
+                &tables,
+                possible_drops.as_ref().unwrap_or(&DropSet::ALL),
+                rng,
+                seeds,
+            )
+            .unwrap_or_else(exit_drop_error);
+
+            if args.json {
+                write_json_document(&args, &analysis);
+            } else {
+                if let Some(warning) = analysis.sample_size_warning() {
+                    eprintln!("{warning}");
+                }
+                print_rates(&args, false, |drop| analysis.rate(drop));
+            }
+        }
+        Command::Drops {
+            action: Some(DropsCommand::Audit),
+            ..
+        } => {
+            #[derive(Serialize)]
+            struct Entry<'a> {
+                enemy: &'a str,
+                sum: u16,
+                discrepancy: i32,
+            }
+
+            // Minor drop chances are scaled to fill the space left by the major drop chances, so
+            // it's the total of all six chances that should be 255.
+            let mut output: Vec<Entry> = drops::ENEMY_DROPS
+                .iter()
+                .map(|(enemy, table)| {
+                    let sum =
+                        table.minor_sum() + table.super_missile as u16 + table.power_bomb as u16;
+                    Entry {
+                        enemy,
+                        sum,
+                        discrepancy: sum as i32 - 0xFF,
+                    }
+                })
+                .filter(|entry| entry.discrepancy != 0)
+                .collect();
+            output.sort_by_key(|entry| entry.enemy);
+
             if args.json {
                 serde_json::to_writer(std::io::stdout(), &output).unwrap();
+            } else if output.is_empty() {
+                println!(
+                    "All {} enemies' drop chances sum to 255.",
+                    drops::ENEMY_DROPS.len()
+                );
+            } else {
+                for Entry {
+                    enemy,
+                    sum,
+                    discrepancy,
+                } in output
+                {
+                    println!("{enemy}: drop chances sum to {sum} ({discrepancy:+})");
+                }
+            }
+        }
+        Command::Completions { shell } => {
+            let mut command = Args::command();
+            let name = command.get_name().to_string();
+            clap_complete::generate(shell, &mut command, name, &mut std::io::stdout());
+        }
+        Command::Between { from, to, hex } => {
+            let rng = args.rng().with_seed(from);
+            let Some(distance) = rng.distance_to(to) else {
+                eprintln!("Seed {to:#06x} is not reachable from {from:#06x}");
+                exit(2);
+            };
+
+            let output: Vec<u16> = rng.seeds_until_loop().take(distance + 1).collect();
+            if args.jsonl {
+                write_json_lines(output).unwrap();
+            } else if args.json {
+                serde_json::to_writer(std::io::stdout(), &output).unwrap();
             } else {
                 for seed in output {
-                    if hex {
-                        println!("{seed:#06x}");
+                    println!("{}", format_seed(seed, hex));
+                }
+            }
+        }
+        Command::Graph { hex } => {
+            if args.json {
+                let successors = args.rng().successor_map();
+                if args.jsonl {
+                    write_json_lines(successors).unwrap();
+                } else {
+                    serde_json::to_writer(std::io::stdout(), &successors).unwrap();
+                }
+            } else {
+                let analysis = args.rng().analyze();
+                for (id, branch) in analysis.branches.iter().enumerate() {
+                    let last = *branch.seeds.last().unwrap();
+                    println!(
+                        "{} -> {} (branch {id})",
+                        format_seed(last, hex),
+                        format_seed(analysis.successor_of(last), hex)
+                    );
+                }
+            }
+        }
+        Command::Classify { target: seed, hex } => {
+            let analysis = args.rng().analyze();
+            let named = [
+                ("reset", Rng::RESET),
+                ("beetom", Rng::BEETOM),
+                ("sidehopper", Rng::SIDEHOPPER),
+                ("polyp", Rng::POLYP),
+            ];
+
+            if args.json {
+                #[derive(Serialize)]
+                struct Named {
+                    name: &'static str,
+                    seed: u16,
+                    shares_loop: bool,
+                }
+                #[derive(Serialize)]
+                struct Output {
+                    seed: u16,
+                    info: SeedInfo,
+                    loop_id: Option<u16>,
+                    named: Vec<Named>,
+                }
+
+                let output = Output {
+                    seed,
+                    info: analysis[seed],
+                    loop_id: analysis.loop_id(seed),
+                    named: named
+                        .iter()
+                        .map(|(name, rng)| Named {
+                            name,
+                            seed: rng.seed,
+                            shares_loop: analysis.shares_loop_with(seed, rng.seed),
+                        })
+                        .collect(),
+                };
+                serde_json::to_writer(std::io::stdout(), &output).unwrap();
+            } else {
+                let seed_str = format_seed(seed, hex);
+                match analysis[seed] {
+                    SeedInfo::Loop { id } => println!("Seed {seed_str} is part of loop {id}"),
+                    SeedInfo::Branch { id } => println!(
+                        "Seed {seed_str} is part of branch {id}, which leads to loop {}",
+                        analysis.branches[id as usize].loop_id
+                    ),
+                    SeedInfo::Unclassified => unreachable!(),
+                }
+                for (name, rng) in named {
+                    let shares = if analysis.shares_loop_with(seed, rng.seed) {
+                        "shares"
                     } else {
-                        println!("{seed}");
-                    }
+                        "does not share"
+                    };
+                    println!(
+                        "{name:>10} ({}) {shares} its loop",
+                        format_seed(rng.seed, hex)
+                    );
                 }
             }
         }
         Command::Drops {
+            action: None,
             count,
             uncorrelated,
             ideal,
             histogram,
+            at_least_one,
+            per_seed,
+            dud_chance,
+            explain,
+            weighted,
+            frames_per_kill,
+            count_range,
+            ref compare,
+            ref calls_range,
+            best_reachable,
+            probabilities,
+            sample,
+            rng_sample_seed,
+            continuous,
             mut loop_id,
             branch,
             all_seeds,
+            ref seed_range,
             ref enemy,
+            ref possible_drops,
             full_energy,
             full_missiles,
             full_supers,
@@ -233,52 +1041,366 @@ fn main() {
             filter_missiles,
             filter_supers,
             filter_pbs,
+            sort,
+            top,
+            min_seeds,
         } => {
-            let Some(drop_table) = drops::ENEMY_DROPS.get(enemy) else {
+            let enemy = enemy
+                .as_deref()
+                .expect("clap requires an enemy without a subcommand");
+            let Some(drop_table) = drops::lookup_enemy(enemy) else {
                 eprintln!("Unknown enemy {enemy}");
                 exit(2)
             };
 
-            if loop_id.is_none() && branch.is_none() && !all_seeds && args.seed.is_none() {
+            let default_loop = loop_id.is_none()
+                && branch.is_none()
+                && !all_seeds
+                && seed_range.is_none()
+                && best_reachable.is_none()
+                && args.seed.is_none();
+            if default_loop {
                 loop_id = Some(0);
             }
-            let rng = args.rng();
+            let mut full = DropSet::new();
+            if full_energy {
+                full |= &DropSet::HEALTH_BOMB;
+            }
+            if full_missiles {
+                full.insert(Drop::Missile);
+            }
+            if full_supers {
+                full.insert(Drop::SuperMissile);
+            }
+            if full_pbs {
+                full.insert(Drop::PowerBomb);
+            }
+            let possible_drops = possible_drops
+                .clone()
+                .unwrap_or(DropSet::ALL)
+                .intersection(&full.complement());
 
-            let seeds: Vec<u16> = if all_seeds {
-                (0..=u16::MAX).collect()
-            } else if let Some(loop_id) = loop_id {
-                let mut analysis = args.rng().analyze();
-                let Some(l) = analysis.loops.get_mut(loop_id) else {
-                    eprintln!("Loop index out of range 0..={}", analysis.loops.len());
-                    exit(2);
+            let select_seeds = |rng: &Rng| -> Vec<u16> {
+                let seeds: Vec<u16> = if all_seeds {
+                    (0..=u16::MAX).collect()
+                } else if let Some(range) = seed_range {
+                    range.clone().collect()
+                } else if let Some(loop_id) = loop_id {
+                    let mut analysis = rng.analyze();
+                    let Some(l) = analysis.loops.get_mut(loop_id) else {
+                        eprintln!("Loop index out of range 0..={}", analysis.loops.len());
+                        exit(2);
+                    };
+                    if default_loop && calls_range.is_none() {
+                        if l.period() == LoopInfo::MAIN_LOOP_PERIOD {
+                            eprintln!("Using the {} loop (loop 0)", l.period());
+                        } else {
+                            eprintln!("Using loop 0 (period {})", l.period());
+                        }
+                    }
+                    std::mem::take(&mut l.seeds)
+                } else if let Some(branch_id) = branch {
+                    let mut analysis = rng.analyze();
+                    let Some(b) = analysis.branches.get_mut(branch_id) else {
+                        eprintln!("Branch index out of range 0..={}", analysis.branches.len());
+                        exit(2);
+                    };
+                    std::mem::take(&mut b.seeds)
+                } else {
+                    rng.seeds_until_loop().collect()
                 };
-                std::mem::take(&mut l.seeds)
-            } else if let Some(branch_id) = branch {
-                let mut analysis = args.rng().analyze();
-                let Some(b) = analysis.branches.get_mut(branch_id) else {
-                    eprintln!("Branch index out of range 0..={}", analysis.branches.len());
-                    exit(2);
+                let seeds = match sample.map(|n| n as usize) {
+                    Some(n) if n < seeds.len() => {
+                        use rand::SeedableRng;
+                        let mut sample_rng = rand::rngs::StdRng::seed_from_u64(rng_sample_seed);
+                        let mut indices =
+                            rand::seq::index::sample(&mut sample_rng, seeds.len(), n).into_vec();
+                        indices.sort_unstable();
+                        if !args.json {
+                            eprintln!(
+                                "Sampled {} of {} seeds",
+                                format_count(n as u64),
+                                format_count(seeds.len() as u64)
+                            );
+                        }
+                        indices.into_iter().map(|i| seeds[i]).collect()
+                    }
+                    _ => seeds,
                 };
-                std::mem::take(&mut b.seeds)
-            } else {
-                rng.seeds_until_loop().collect()
+                seeds
             };
 
-            let mut possible_drops = DropSet::ALL;
-            if full_energy {
-                possible_drops -= &DropSet::from_iter([Drop::SmallEnergy, Drop::BigEnergy]);
+            if let Some(range) = calls_range {
+                let entries: Vec<(usize, DropAnalysis)> = range
+                    .clone()
+                    .map(|calls_per_frame| {
+                        let rng = args.rng().with_calls_per_frame(calls_per_frame);
+                        let seeds = select_seeds(&rng);
+                        let analysis = if uncorrelated {
+                            drops::analysis::analyze_uncorrelated(
+                                drop_table,
+                                &possible_drops,
+                                count,
+                                seeds,
+                            )
+                        } else if continuous {
+                            drops::analysis::analyze_continuous(
+                                drop_table,
+                                &possible_drops,
+                                count,
+                                rng.with_seed(seeds[0]),
+                                seeds.len() as u32,
+                            )
+                        } else {
+                            drops::analysis::analyze_correlated(
+                                drop_table,
+                                &possible_drops,
+                                count,
+                                rng,
+                                seeds,
+                            )
+                        };
+                        (calls_per_frame, analysis.unwrap_or_else(exit_drop_error))
+                    })
+                    .collect();
+
+                let resources = Drop::ALL.into_iter().filter(|&drop| drop != Drop::Nothing);
+                if args.json {
+                    #[derive(Serialize)]
+                    struct Entry<'a> {
+                        calls_per_frame: usize,
+                        #[serde(flatten)]
+                        analysis: &'a DropAnalysis,
+                    }
+
+                    let output: Vec<Entry> = entries
+                        .iter()
+                        .map(|(calls_per_frame, analysis)| Entry {
+                            calls_per_frame: *calls_per_frame,
+                            analysis,
+                        })
+                        .collect();
+                    if args.jsonl {
+                        write_json_lines(output).unwrap();
+                    } else {
+                        write_json_document(&args, &output);
+                    }
+                } else if args.markdown {
+                    println!("| Calls | Seeds | Small E | Big E | Missile | Super | PB |");
+                    println!("|------:|------:|--------:|------:|--------:|------:|---:|");
+                    for (calls_per_frame, analysis) in &entries {
+                        print!(
+                            "| {calls_per_frame} | {} |",
+                            format_count(analysis.seeds as u64)
+                        );
+                        for drop in resources.clone() {
+                            print!(" {:.3} |", analysis.rate(drop));
+                        }
+                        println!();
+                    }
+                } else {
+                    println!("Calls|  Seeds| Small E|   Big E| Missile|   Super|      PB");
+                    println!("-----+-------+--------+--------+--------+--------+--------");
+                    for (calls_per_frame, analysis) in &entries {
+                        print!(
+                            "{calls_per_frame:>5}|{:>7}",
+                            format_count(analysis.seeds as u64)
+                        );
+                        for drop in resources.clone() {
+                            print!("|{:>8.3}", analysis.rate(drop));
+                        }
+                        println!();
+                    }
+                }
+                return;
             }
-            if full_missiles {
-                possible_drops -= &DropSet::from_iter([Drop::Missile]);
+
+            let rng = args.rng();
+
+            // These modes don't use the selected seeds, so handle them before selecting seeds
+            // (which can be slow, and reports the default loop).
+            if let Some(within) = best_reachable {
+                let analysis = rng.analyze_reachable();
+                let drops_at = |seed| {
+                    drops::analysis::analyze_correlated(
+                        drop_table,
+                        &possible_drops,
+                        count,
+                        rng.clone(),
+                        std::iter::once(seed),
+                    )
+                    .unwrap_or_else(exit_drop_error)
+                };
+                let best = analysis
+                    .best_reachable_seed(rng.seed, within, |seed| {
+                        let drops = drops_at(seed);
+                        (drops.total_drops() - drops.nothing) as f32
+                    })
+                    .expect("drop counts are never NaN");
+                let frames = rng.distance_to(best).expect("the best seed is reachable");
+                let drops = drops_at(best);
+
+                if args.json {
+                    #[derive(Serialize)]
+                    struct Output {
+                        seed: u16,
+                        frames: usize,
+                        #[serde(flatten)]
+                        drops: DropAnalysis,
+                    }
+                    let output = Output {
+                        seed: best,
+                        frames,
+                        drops,
+                    };
+                    write_json_document(&args, &output);
+                } else {
+                    println!(
+                        "Best seed: {best:#06x} ({frames} frames from {:#06x})",
+                        rng.seed
+                    );
+                    println!();
+                    print_rates(&args, false, |drop| drops.get(drop) as f32);
+                }
+                return;
             }
-            if full_supers {
-                possible_drops -= &DropSet::from_iter([Drop::SuperMissile]);
+            if explain {
+                // The drop routine calls RNG once before generating each drop.
+                let mut rng = rng.clone();
+                let discarded = rng.roll();
+                let (drop, explanation) = drop_table
+                    .roll_one_explained(&mut rng, &possible_drops)
+                    .unwrap_or_else(exit_drop_error);
+
+                if args.json {
+                    #[derive(Serialize)]
+                    struct Output {
+                        drop: Drop,
+                        #[serde(flatten)]
+                        explanation: drops::DropExplanation,
+                    }
+                    let output = Output { drop, explanation };
+                    serde_json::to_writer(std::io::stdout(), &output).unwrap();
+                } else {
+                    println!("Discarded roll: {discarded:#06x}");
+                    for &roll in &explanation.rolls[..explanation.rolls.len() - 1] {
+                        println!("Rerolled {roll:#06x}, since its low byte is zero");
+                    }
+                    println!(
+                        "Drop roll: {:#06x} (low byte {})",
+                        explanation.rolls.last().unwrap(),
+                        explanation.random
+                    );
+                    println!(
+                        "Pooled chances: minor {}, major {}",
+                        explanation.pooled_minor, explanation.pooled_major
+                    );
+                    println!();
+                    println!("Drop         | Range");
+                    println!("-------------+--------");
+                    let mut low = 1;
+                    for &(drop, threshold) in &explanation.thresholds {
+                        let name = format!("{drop:?}");
+                        if threshold < low {
+                            println!("{name:>12} | (none)");
+                            continue;
+                        }
+                        let marker = if (low..=threshold).contains(&explanation.random) {
+                            " <--"
+                        } else {
+                            ""
+                        };
+                        println!("{name:>12} | {low:>3}..={threshold}{marker}");
+                        low = threshold + 1;
+                    }
+                    println!();
+                    println!("Result: {drop:?}");
+                }
+                return;
             }
-            if full_pbs {
-                possible_drops -= &DropSet::from_iter([Drop::PowerBomb]);
+            if ideal {
+                let rates = drop_table.expected_drops_all(&possible_drops, count);
+                let frames = frames_per_kill.unwrap_or(1) as f32;
+                print_rates(&args, frames_per_kill.is_some(), |drop| {
+                    rates.get(drop) as f32 / frames
+                });
+                return;
             }
 
-            if histogram {
+            let seeds = select_seeds(&rng);
+            if let Some(drop) = at_least_one {
+                let chance = drops::analysis::chance_at_least_one(
+                    drop_table,
+                    &possible_drops,
+                    count,
+                    rng.clone(),
+                    seeds,
+                    drop,
+                )
+                .unwrap_or_else(exit_drop_error);
+
+                if args.json {
+                    serde_json::to_writer(std::io::stdout(), &chance).unwrap();
+                } else {
+                    println!("{chance:.3}");
+                }
+            } else if per_seed {
+                let output = drops::analysis::per_seed(
+                    drop_table,
+                    &possible_drops,
+                    count,
+                    rng.clone(),
+                    seeds,
+                )
+                .unwrap_or_else(exit_drop_error);
+
+                if args.json {
+                    #[derive(Serialize)]
+                    struct Entry {
+                        seed: u16,
+                        #[serde(flatten)]
+                        analysis: DropAnalysis,
+                    }
+
+                    let output: Vec<Entry> = output
+                        .into_iter()
+                        .map(|(seed, analysis)| Entry { seed, analysis })
+                        .collect();
+                    if args.jsonl {
+                        write_json_lines(output).unwrap();
+                    } else {
+                        serde_json::to_writer(std::io::stdout(), &output).unwrap();
+                    }
+                } else {
+                    println!("  Seed| Small E|   Big E| Missile|   Super|      PB");
+                    println!("------+--------+--------+--------+--------+--------");
+                    for (seed, analysis) in output {
+                        println!(
+                            "{seed:#06x}|{:>8}|{:>8}|{:>8}|{:>8}|{:>8}",
+                            analysis.small_energy,
+                            analysis.big_energy,
+                            analysis.missile,
+                            analysis.super_missile,
+                            analysis.power_bomb
+                        );
+                    }
+                }
+            } else if dud_chance {
+                let chance = drops::analysis::chance_nothing(
+                    drop_table,
+                    &possible_drops,
+                    count,
+                    rng.clone(),
+                    seeds,
+                )
+                .unwrap_or_else(exit_drop_error);
+
+                if args.json {
+                    serde_json::to_writer(std::io::stdout(), &chance).unwrap();
+                } else {
+                    println!("{chance:.3}");
+                }
+            } else if histogram {
                 let no_filters =
                     !filter_energy && !filter_missiles && !filter_pbs && !filter_supers;
                 let include_energy = no_filters || filter_energy;
@@ -286,45 +1408,95 @@ fn main() {
                 let include_supers = no_filters || filter_supers;
                 let include_pbs = no_filters || filter_pbs;
 
-                let mut histogram = HashMap::<DropAnalysis, u32>::new();
-                for &seed in &seeds {
-                    let mut analysis = drops::analysis::analyze_correlated(
+                let mut counted = DropSet::new();
+                if include_energy {
+                    counted |= &DropSet::HEALTH_BOMB;
+                }
+                if include_missiles {
+                    counted.insert(Drop::Missile);
+                }
+                if include_supers {
+                    counted.insert(Drop::SuperMissile);
+                }
+                if include_pbs {
+                    counted.insert(Drop::PowerBomb);
+                }
+
+                #[cfg(feature = "rayon")]
+                let histogram = drops::analysis::par_histogram(
+                    drop_table,
+                    &possible_drops,
+                    count,
+                    rng.clone(),
+                    &seeds,
+                    &counted,
+                );
+                #[cfg(not(feature = "rayon"))]
+                let histogram = {
+                    let mut progress = args.progress(seeds.len());
+                    let histogram = drops::analysis::histogram(
                         drop_table,
                         &possible_drops,
                         count,
                         rng.clone(),
-                        std::iter::once(seed),
+                        seeds.iter().copied().inspect(|_| progress.tick()),
+                        &counted,
                     );
-                    analysis.nothing = 0;
-                    if !include_energy {
-                        analysis.small_energy = 0;
-                        analysis.big_energy = 0;
+                    progress.finish();
+                    histogram
+                };
+                let mut histogram = histogram.unwrap_or_else(exit_drop_error);
+                histogram.sort_by_key(|entry| Reverse((sort.key(entry), entry.seeds)));
+                if let Some(min_seeds) = min_seeds {
+                    histogram.retain(|entry| entry.seeds >= min_seeds);
+                }
+                if let Some(top) = top {
+                    histogram.truncate(top);
+                }
+
+                if args.jsonl {
+                    write_json_lines(histogram).unwrap();
+                } else if args.json {
+                    serde_json::to_writer(std::io::stdout(), &histogram).unwrap();
+                } else if args.markdown {
+                    let mut columns = vec!["Seeds", "%"];
+                    if include_energy {
+                        columns.extend(["Small E", "Big E"]);
                     }
-                    if !include_missiles {
-                        analysis.missile = 0;
+                    if include_missiles {
+                        columns.push("Missile");
                     }
-                    if !include_supers {
-                        analysis.super_missile = 0;
+                    if include_supers {
+                        columns.push("Super");
                     }
-                    if !include_pbs {
-                        analysis.power_bomb = 0;
+                    if include_pbs {
+                        columns.push("PB");
                     }
-                    *histogram.entry(analysis).or_default() += 1;
-                }
-
-                let mut histogram: Vec<_> = histogram
-                    .into_iter()
-                    .map(|(entry, count)| DropAnalysis {
-                        seeds: count,
-                        ..entry
-                    })
-                    .collect();
-                histogram.sort_by_key(|DropAnalysis { seeds, .. }| u32::MAX - *seeds);
+                    println!("| {} |", columns.join(" | "));
+                    println!("|{}", "---:|".repeat(columns.len()));
 
-                if args.json {
-                    serde_json::to_writer(std::io::stdout(), &histogram).unwrap();
+                    for entry in histogram {
+                        print!(
+                            "| {} | {}% |",
+                            format_count(entry.seeds as u64),
+                            format_percentage(entry.seeds, seeds.len() as u32).trim_start(),
+                        );
+                        if include_energy {
+                            print!(" {} | {} |", entry.small_energy, entry.big_energy);
+                        }
+                        if include_missiles {
+                            print!(" {} |", entry.missile);
+                        }
+                        if include_supers {
+                            print!(" {} |", entry.super_missile);
+                        }
+                        if include_pbs {
+                            print!(" {} |", entry.power_bomb);
+                        }
+                        println!();
+                    }
                 } else {
-                    print!("#            ");
+                    print!("#             ");
                     if include_energy {
                         print!("| Small E|   Big E");
                     }
@@ -339,7 +1511,7 @@ fn main() {
                     }
                     println!();
 
-                    print!("-------------");
+                    print!("--------------");
                     if include_energy {
                         print!("+--------+--------");
                     }
@@ -356,8 +1528,8 @@ fn main() {
 
                     for entry in histogram {
                         print!(
-                            "{:>5} ({}%)",
-                            entry.seeds,
+                            "{:>6} ({}%)",
+                            format_count(entry.seeds as u64),
                             format_percentage(entry.seeds, seeds.len() as u32),
                         );
                         if include_energy {
@@ -375,80 +1547,321 @@ fn main() {
                         println!();
                     }
                 }
-            } else if ideal {
-                let get_stat = |drop| drop_table.ideal_drops_per_farm(drop, &possible_drops, count);
+            } else if weighted {
+                let visits = args.rng().analyze().visit_counts();
+                let rates = drops::analysis::analyze_weighted(
+                    drop_table,
+                    &possible_drops,
+                    count,
+                    rng.clone(),
+                    seeds
+                        .into_iter()
+                        .map(|seed| (seed, visits[seed as usize] as f64)),
+                )
+                .unwrap_or_else(exit_drop_error);
+
+                let frames = frames_per_kill.unwrap_or(1) as f32;
+                print_rates(&args, frames_per_kill.is_some(), |drop| {
+                    rates.get(drop) as f32 / frames
+                });
+            } else if let Some(other) = compare {
+                let Some(other_table) = drops::lookup_enemy(other) else {
+                    eprintln!("Unknown enemy {other}");
+                    exit(2)
+                };
+
+                let analyze = |table| {
+                    if continuous {
+                        drops::analysis::analyze_continuous(
+                            table,
+                            &possible_drops,
+                            count,
+                            rng.with_seed(seeds[0]),
+                            seeds.len() as u32,
+                        )
+                    } else {
+                        drops::analysis::analyze_correlated(
+                            table,
+                            &possible_drops,
+                            count,
+                            rng.clone(),
+                            seeds.iter().copied(),
+                        )
+                    }
+                    .unwrap_or_else(exit_drop_error)
+                };
+                let a = analyze(drop_table);
+                let b = analyze(other_table);
+                let delta = |drop| b.rate(drop) - a.rate(drop);
 
                 if args.json {
                     #[derive(Serialize)]
                     struct Output {
-                        small_energy: f32,
-                        big_energy: f32,
-                        missile: f32,
-                        super_missile: f32,
-                        power_bomb: f32,
+                        enemy_a: DropAnalysis,
+                        enemy_b: DropAnalysis,
+                        delta: DropRates,
                     }
-
                     let output = Output {
-                        small_energy: get_stat(Drop::SmallEnergy),
-                        big_energy: get_stat(Drop::BigEnergy),
-                        missile: get_stat(Drop::Missile),
-                        super_missile: get_stat(Drop::SuperMissile),
-                        power_bomb: get_stat(Drop::PowerBomb),
+                        delta: DropRates {
+                            nothing: delta(Drop::Nothing) as f64,
+                            small_energy: delta(Drop::SmallEnergy) as f64,
+                            big_energy: delta(Drop::BigEnergy) as f64,
+                            missile: delta(Drop::Missile) as f64,
+                            super_missile: delta(Drop::SuperMissile) as f64,
+                            power_bomb: delta(Drop::PowerBomb) as f64,
+                        },
+                        enemy_a: a,
+                        enemy_b: b,
                     };
-                    serde_json::to_writer_pretty(std::io::stdout(), &output).unwrap();
+                    write_json_document(&args, &output);
                 } else {
-                    let print_stat = |name, drop| println!("{name:>8} | {:.3}", get_stat(drop));
-
-                    println!("Resource | Drops");
-                    println!("---------+------");
-                    print_stat("Small E", Drop::SmallEnergy);
-                    print_stat("Big E", Drop::BigEnergy);
-                    print_stat("Missile", Drop::Missile);
-                    print_stat("Super", Drop::SuperMissile);
-                    print_stat("PB", Drop::PowerBomb);
-                };
+                    if let Some(warning) = a.sample_size_warning() {
+                        eprintln!("{warning}");
+                    }
+
+                    let stats = [
+                        ("Small E", Drop::SmallEnergy),
+                        ("Big E", Drop::BigEnergy),
+                        ("Missile", Drop::Missile),
+                        ("Super", Drop::SuperMissile),
+                        ("PB", Drop::PowerBomb),
+                    ];
+                    let width_a = enemy.len().max(6);
+                    let width_b = other.len().max(6);
+                    if args.markdown {
+                        println!("| Resource | {enemy} | {other} | Delta |");
+                        println!("|:---------|------:|------:|------:|");
+                        for (name, drop) in stats {
+                            println!(
+                                "| {name} | {:.3} | {:.3} | {:+.3} |",
+                                a.rate(drop),
+                                b.rate(drop),
+                                delta(drop)
+                            );
+                        }
+                    } else {
+                        println!("Resource | {enemy:>width_a$} | {other:>width_b$} |  Delta");
+                        println!(
+                            "---------+-{}-+-{}-+-------",
+                            "-".repeat(width_a),
+                            "-".repeat(width_b)
+                        );
+                        for (name, drop) in stats {
+                            println!(
+                                "{name:>8} | {:>width_a$.3} | {:>width_b$.3} | {:>+6.3}",
+                                a.rate(drop),
+                                b.rate(drop),
+                                delta(drop)
+                            );
+                        }
+                    }
+                }
+            } else if let Some(max) = count_range {
+                let mut progress = args.progress(seeds.len() * max as usize);
+                let analyses: Vec<DropAnalysis> = (1..=max)
+                    .map(|count| {
+                        if continuous {
+                            drops::analysis::analyze_continuous(
+                                drop_table,
+                                &possible_drops,
+                                count,
+                                rng.with_seed(seeds[0]),
+                                seeds.len() as u32,
+                            )
+                        } else if uncorrelated {
+                            drops::analysis::analyze_uncorrelated(
+                                drop_table,
+                                &possible_drops,
+                                count,
+                                seeds.iter().copied(),
+                            )
+                        } else {
+                            drops::analysis::analyze_correlated(
+                                drop_table,
+                                &possible_drops,
+                                count,
+                                rng.clone(),
+                                seeds.iter().copied().inspect(|_| progress.tick()),
+                            )
+                        }
+                        .unwrap_or_else(exit_drop_error)
+                    })
+                    .collect();
+                progress.finish();
+
+                let resources = Drop::ALL.into_iter().filter(|&drop| drop != Drop::Nothing);
+                if args.json {
+                    #[derive(Serialize)]
+                    struct Entry<'a> {
+                        count: u32,
+                        #[serde(flatten)]
+                        analysis: &'a DropAnalysis,
+                    }
+
+                    let output: Vec<Entry> = (1..)
+                        .zip(&analyses)
+                        .map(|(count, analysis)| Entry { count, analysis })
+                        .collect();
+                    if args.jsonl {
+                        write_json_lines(output).unwrap();
+                    } else {
+                        write_json_document(&args, &output);
+                    }
+                } else if args.markdown {
+                    println!("| Count | Small E | Big E | Missile | Super | PB |");
+                    println!("|------:|--------:|------:|--------:|------:|---:|");
+                    for (count, analysis) in (1..).zip(&analyses) {
+                        print!("| {count} |");
+                        for drop in resources.clone() {
+                            print!(" {:.3} |", analysis.rate(drop));
+                        }
+                        println!();
+                    }
+                } else {
+                    println!("Count| Small E|   Big E| Missile|   Super|      PB");
+                    println!("-----+--------+--------+--------+--------+--------");
+                    for (count, analysis) in (1..).zip(&analyses) {
+                        print!("{count:>5}");
+                        for drop in resources.clone() {
+                            print!("|{:>8.3}", analysis.rate(drop));
+                        }
+                        println!();
+                    }
+                }
             } else {
                 let analysis = if uncorrelated {
                     drops::analysis::analyze_uncorrelated(drop_table, &possible_drops, count, seeds)
+                        .unwrap_or_else(exit_drop_error)
+                } else if continuous {
+                    drops::analysis::analyze_continuous(
+                        drop_table,
+                        &possible_drops,
+                        count,
+                        rng.with_seed(seeds[0]),
+                        seeds.len() as u32,
+                    )
+                    .unwrap_or_else(exit_drop_error)
                 } else {
-                    drops::analysis::analyze_correlated(
+                    let mut progress = args.progress(seeds.len());
+                    let analysis = drops::analysis::analyze_correlated(
                         drop_table,
                         &possible_drops,
                         count,
                         rng.clone(),
-                        seeds,
-                    )
+                        seeds.into_iter().inspect(|_| progress.tick()),
+                    );
+                    progress.finish();
+                    analysis.unwrap_or_else(exit_drop_error)
                 };
 
-                if args.json {
-                    serde_json::to_writer_pretty(std::io::stdout(), &analysis).unwrap();
-                } else {
-                    let print_stat = |name, stat| {
-                        println!("{name:>8} | {:.3}", stat as f32 / analysis.seeds as f32)
-                    };
+                if !args.json {
+                    if let Some(warning) = analysis.sample_size_warning() {
+                        eprintln!("{warning}");
+                    }
+                }
 
-                    println!("Resource | Drops");
-                    println!("---------+------");
-                    print_stat("Small E", analysis.small_energy);
-                    print_stat("Big E", analysis.big_energy);
-                    print_stat("Missile", analysis.missile);
-                    print_stat("Super", analysis.super_missile);
-                    print_stat("PB", analysis.power_bomb);
+                if probabilities {
+                    print_probabilities(&args, &analysis);
+                } else if args.json && frames_per_kill.is_none() {
+                    write_json_document(&args, &analysis);
+                } else {
+                    let frames = frames_per_kill.unwrap_or(1) as f32;
+                    print_rates(&args, frames_per_kill.is_some(), |drop| {
+                        analysis.rate(drop) / frames
+                    });
                 }
             }
         }
     }
 }
 
-fn format_percentage(num: u32, denom: u32) -> String {
-    let percentage = (num as f32) / (denom as f32) * 100.;
-
-    let digits_before_decimal = (percentage.floor() + 0.1).log10().max(1.).ceil() as usize;
-    let digits_after_decimal = 3 - digits_before_decimal;
-    format!(
-        "{:b$.a$}",
-        percentage,
-        b = digits_before_decimal,
-        a = digits_after_decimal
-    )
+/// Prints the mean number of drops of each resource per kill (or per frame, if `per_frame` is
+/// set), as JSON or as a table.
+fn print_rates(args: &Args, per_frame: bool, rate: impl Fn(Drop) -> f32) {
+    if args.json {
+        #[derive(Serialize)]
+        struct Output {
+            small_energy: f32,
+            big_energy: f32,
+            missile: f32,
+            super_missile: f32,
+            power_bomb: f32,
+        }
+
+        let output = Output {
+            small_energy: rate(Drop::SmallEnergy),
+            big_energy: rate(Drop::BigEnergy),
+            missile: rate(Drop::Missile),
+            super_missile: rate(Drop::SuperMissile),
+            power_bomb: rate(Drop::PowerBomb),
+        };
+        write_json_document(args, &output);
+        return;
+    }
+
+    let stats = [
+        ("Small E", rate(Drop::SmallEnergy)),
+        ("Big E", rate(Drop::BigEnergy)),
+        ("Missile", rate(Drop::Missile)),
+        ("Super", rate(Drop::SuperMissile)),
+        ("PB", rate(Drop::PowerBomb)),
+    ];
+    // Per-frame rates are small, so show more digits.
+    let (unit, precision) = if per_frame {
+        ("Drops/frame", 5)
+    } else {
+        ("Drops", 3)
+    };
+    let dashes = "-".repeat(unit.len() + 1);
+    if args.markdown {
+        println!("| Resource | {unit} |");
+        println!("|:---------|{dashes}:|");
+        for (name, stat) in stats {
+            println!("| {name} | {stat:.precision$} |");
+        }
+    } else {
+        println!("Resource | {unit}");
+        println!("---------+{dashes}");
+        for (name, stat) in stats {
+            println!("{name:>8} | {stat:.precision$}");
+        }
+    }
+}
+
+/// Prints the chance of each drop, including nothing, per drop generated, as JSON or as a table.
+fn print_probabilities(args: &Args, analysis: &DropAnalysis) {
+    if args.json {
+        let output = DropRates {
+            nothing: analysis.probability(Drop::Nothing) as f64,
+            small_energy: analysis.probability(Drop::SmallEnergy) as f64,
+            big_energy: analysis.probability(Drop::BigEnergy) as f64,
+            missile: analysis.probability(Drop::Missile) as f64,
+            super_missile: analysis.probability(Drop::SuperMissile) as f64,
+            power_bomb: analysis.probability(Drop::PowerBomb) as f64,
+        };
+        write_json_document(args, &output);
+        return;
+    }
+
+    let stats = [
+        ("Small E", Drop::SmallEnergy),
+        ("Big E", Drop::BigEnergy),
+        ("Missile", Drop::Missile),
+        ("Super", Drop::SuperMissile),
+        ("PB", Drop::PowerBomb),
+        ("Nothing", Drop::Nothing),
+    ];
+    if args.markdown {
+        println!("| Resource | Chance |");
+        println!("|:---------|-------:|");
+        for (name, drop) in stats {
+            println!("| {name} | {:.3} |", analysis.probability(drop));
+        }
+    } else {
+        println!("Resource | Chance");
+        println!("---------+-------");
+        for (name, drop) in stats {
+            println!("{name:>8} | {:.3}", analysis.probability(drop));
+        }
+    }
 }
@@ -1,19 +1,105 @@
-use std::collections::HashSet;
+use std::{
+    collections::HashSet,
+    num::ParseIntError,
+    ops::{RangeInclusive, Sub},
+    str::FromStr,
+};
 
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 /// Super Metroid's PRNG function.
 ///
 /// A translation of https://patrickjohnston.org/bank/80?just=8111
 pub fn rng1(seed: u16) -> u16 {
-    let result = (seed & 0xFF) * 5;
-    let hi = (((seed >> 8) & 0xFF) * 5) & 0xFF;
-    let result = result as u32 + ((hi as u32) << 8) + 0x100;
-    ((result >> 16) + result + 0x11) as u16
+    RngParams::VANILLA.next(seed)
+}
+
+/// Parses a seed, either a hexadecimal number prefixed with '0x' (`0x0061`) or a decimal number
+/// (`97`). The prefix and hex digits are case-insensitive.
+pub fn parse_seed(s: &str) -> Result<u16, ParseIntError> {
+    let n = s.to_lowercase();
+    match n.strip_prefix("0x") {
+        Some(hex) => u16::from_str_radix(hex, 16),
+        None => n.parse(),
+    }
+}
+
+/// Parses a range, either exclusive (`START..END`) or inclusive (`START..=END`), using
+/// `parse_bound` to parse each (trimmed) bound. Returns an error if the range is empty.
+pub fn parse_range<T>(
+    s: &str,
+    parse_bound: impl Fn(&str) -> Result<T, String>,
+) -> Result<RangeInclusive<T>, String>
+where
+    T: Copy + Ord + Sub<Output = T> + From<u8>,
+{
+    let Some((start, end)) = s.split_once("..") else {
+        return Err(format!(
+            "invalid range '{s}' (expected START..END or START..=END)"
+        ));
+    };
+    let start = parse_bound(start.trim())?;
+    let (end, inclusive) = match end.strip_prefix('=') {
+        Some(end) => (parse_bound(end.trim())?, true),
+        None => (parse_bound(end.trim())?, false),
+    };
+
+    if end < start || (end == start && !inclusive) {
+        return Err(format!("range '{s}' is empty"));
+    }
+    Ok(start..=if inclusive { end } else { end - T::from(1) })
+}
+
+/// Parses a range of seeds, either exclusive (`0x2000..0x2100`) or inclusive (`0x2000..=0x20ff`).
+///
+/// Each bound can be a hexadecimal number prefixed with '0x' or a decimal number. Returns an error
+/// if the range contains no seeds.
+pub fn parse_seed_range(s: &str) -> Result<RangeInclusive<u16>, String> {
+    parse_range(s, |bound| {
+        parse_seed(bound).map_err(|e| format!("invalid seed '{bound}': {e}"))
+    })
+}
+
+/// The constants used by the PRNG function.
+///
+/// Some romhacks modify these; `RngParams::VANILLA` matches the unmodified game.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RngParams {
+    /// The value each byte of the seed is multiplied by.
+    pub multiplier: u16,
+
+    /// The value added to the product before the carry is folded back in.
+    pub carry_increment: u16,
+
+    /// The value added to produce the final result.
+    pub increment: u16,
+}
+
+impl RngParams {
+    /// The PRNG constants used by vanilla Super Metroid.
+    pub const VANILLA: RngParams = RngParams {
+        multiplier: 5,
+        carry_increment: 0x100,
+        increment: 0x11,
+    };
+
+    /// Computes the seed following `seed` using these parameters.
+    pub const fn next(&self, seed: u16) -> u16 {
+        let result = (seed & 0xFF).wrapping_mul(self.multiplier);
+        let hi = ((seed >> 8) & 0xFF).wrapping_mul(self.multiplier) & 0xFF;
+        let result = result as u32 + ((hi as u32) << 8) + self.carry_increment as u32;
+        ((result >> 16) + result + self.increment as u32) as u16
+    }
+}
+
+impl Default for RngParams {
+    fn default() -> Self {
+        RngParams::VANILLA
+    }
 }
 
 /// Represents the state & parameters of the random number generator.
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Rng {
     /// The current seed value ($05E5).
     pub seed: u16,
@@ -28,7 +114,13 @@ pub struct Rng {
     ///
     /// Useful for simulating other enemies that may call RNG. Should usually be at least 1, since
     /// the game's main loop calls RNG once per frame.
+    ///
+    /// A value of 0 is permitted. Without XBA, the seed then never changes, so every seed forms a
+    /// loop of period 1 (and `seeds_until_loop` yields only the current seed).
     pub calls_per_frame: usize,
+
+    /// The constants used by the PRNG function.
+    pub params: RngParams,
 }
 
 impl Rng {
@@ -47,22 +139,71 @@ impl Rng {
         Rng { seed, ..*self }
     }
 
+    /// Returns another `Rng` instance with XBA enabled or disabled.
+    pub fn with_xba(self, xba: bool) -> Rng {
+        Rng { xba, ..self }
+    }
+
+    /// Returns another `Rng` instance that simulates `calls_per_frame` RNG calls per frame.
+    pub fn with_calls_per_frame(self, calls_per_frame: usize) -> Rng {
+        Rng {
+            calls_per_frame,
+            ..self
+        }
+    }
+
     /// Generates a new random number, updating the seed.
     pub fn roll(&mut self) -> u16 {
-        self.seed = rng1(self.seed);
+        self.seed = self.params.next(self.seed);
         self.seed
     }
 
+    /// Generates a new random number in the range `0..n`, updating the seed.
+    ///
+    /// This reduces the full 16-bit output modulo `n`. For powers of two, this is the same as
+    /// masking off the low bits (`AND #n-1`), which is how most game routines that pick from a
+    /// small range reduce the output; routines that reduce it some other way must be simulated
+    /// with `roll` directly.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is 0.
+    pub fn roll_range(&mut self, n: u16) -> u16 {
+        self.roll() % n
+    }
+
+    /// Generates `n` random numbers, updating the seed, and returns each of them in order.
+    ///
+    /// Unlike `frame_advance`, this ignores `calls_per_frame` and `xba`.
+    pub fn roll_n(&mut self, n: usize) -> Vec<u16> {
+        (0..n).map(|_| self.roll()).collect()
+    }
+
     /// Advances to the next simulated frame by applying `calls_per_frame` and XBA.
     pub fn frame_advance(&mut self) {
         for _ in 0..self.calls_per_frame {
             self.roll();
         }
         if self.xba {
-            self.seed = self.seed.swap_bytes();
+            self.apply_xba();
         }
     }
 
+    /// Swaps the bytes of the seed, as the XBA at the end of each frame in an XBA room does.
+    ///
+    /// `frame_advance` does this automatically when `xba` is set; calling it directly allows
+    /// simulating a single swap at a precise point, such as when moving between acid and
+    /// non-acid rooms. Applying it twice has no effect.
+    pub fn apply_xba(&mut self) {
+        self.seed = self.seed.swap_bytes();
+    }
+
+    /// Enables XBA if it was disabled, or disables it if it was enabled, so that subsequent calls
+    /// to `frame_advance` simulate a room transition.
+    pub fn toggle_xba(&mut self) {
+        self.xba = !self.xba;
+    }
+
     /// Returns an iterator over all seeds between the current state and the first repeated seed.
     pub fn seeds_until_loop(&self) -> impl Iterator<Item = u16> {
         struct State {
@@ -88,11 +229,212 @@ impl Rng {
         }
     }
 
+    /// Returns an endless iterator over the seed on each frame, paired with the frame number,
+    /// starting with `(0, self.seed)`.
+    pub fn enumerated_frames(&self) -> impl Iterator<Item = (u64, u16)> {
+        let mut rng = self.clone();
+        (0u64..).map(move |frame| {
+            let seed = rng.seed;
+            rng.frame_advance();
+            (frame, seed)
+        })
+    }
+
+    /// Returns the tail length and period of the sequence of seeds starting at `start`, using the
+    /// parameters of this RNG.
+    ///
+    /// The tail length is the number of frames before `start` enters a loop (0 if `start` is itself
+    /// part of a loop), and the period is the length of that loop. Uses Brent's cycle detection
+    /// algorithm, so it is much cheaper than a full `analyze` when only one seed is of interest.
+    pub fn cycle_of(&self, start: u16) -> (u64, u64) {
+        let next = |seed| {
+            let mut rng = self.with_seed(seed);
+            rng.frame_advance();
+            rng.seed
+        };
+
+        // Find the period by searching successive powers of two.
+        let mut power = 1;
+        let mut period = 1;
+        let mut tortoise = start;
+        let mut hare = next(start);
+        while tortoise != hare {
+            if power == period {
+                tortoise = hare;
+                power *= 2;
+                period = 0;
+            }
+            hare = next(hare);
+            period += 1;
+        }
+
+        // Find the start of the loop by walking two pointers `period` frames apart.
+        let mut tail = 0;
+        tortoise = start;
+        hare = start;
+        for _ in 0..period {
+            hare = next(hare);
+        }
+        while tortoise != hare {
+            tortoise = next(tortoise);
+            hare = next(hare);
+            tail += 1;
+        }
+
+        (tail, period)
+    }
+
+    /// Returns the seeds of the loop the current seed eventually enters, in order, starting with
+    /// the first seed of the loop that is reached.
+    ///
+    /// Uses `cycle_of`, so this is much cheaper than a full `analyze` when only one loop matters.
+    pub fn loop_seeds(&self) -> Vec<u16> {
+        let (tail, period) = self.cycle_of(self.seed);
+        self.enumerated_frames()
+            .skip(tail as usize)
+            .take(period as usize)
+            .map(|(_, seed)| seed)
+            .collect()
+    }
+
+    /// Tallies the seeds of the loop the current seed eventually enters (see `loop_seeds`) into
+    /// `buckets` bins of equal width across the range of seeds, in ascending order.
+    ///
+    /// This measures how uniformly the RNG output is distributed over one full period.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `buckets` is 0.
+    pub fn output_histogram(&self, buckets: usize) -> Vec<u64> {
+        assert!(buckets > 0, "a histogram needs at least one bucket");
+
+        let mut histogram = vec![0; buckets];
+        for seed in self.loop_seeds() {
+            histogram[seed as usize * buckets / 0x10000] += 1;
+        }
+        histogram
+    }
+
+    /// Returns whether the current seed is part of a loop (rather than a branch leading into one).
+    ///
+    /// Uses `cycle_of`, so this is much cheaper than a full `analyze`.
+    pub fn is_in_loop(&self) -> bool {
+        self.cycle_of(self.seed).0 == 0
+    }
+
+    /// Returns the seed after `frames` frame advances, without updating the seed.
+    ///
+    /// Since every seed eventually enters a loop, this takes at most O(65536) steps no matter how
+    /// large `frames` is. Reducing `frames` modulo the loop's period works the same with or
+    /// without XBA, so no jump table is needed.
+    pub fn seed_after(&self, mut frames: u64) -> u16 {
+        if frames > 0x10000 {
+            let (tail, period) = self.cycle_of(self.seed);
+            frames = tail + (frames - tail) % period;
+        }
+
+        let mut rng = self.clone();
+        for _ in 0..frames {
+            rng.frame_advance();
+        }
+        rng.seed
+    }
+
+    /// Returns the seed that follows each seed after one frame, indexed by seed.
+    pub fn successor_map(&self) -> Vec<u16> {
+        (0..=0xFFFFu16)
+            .map(|seed| {
+                let mut rng = self.with_seed(seed);
+                rng.frame_advance();
+                rng.seed
+            })
+            .collect()
+    }
+
+    /// Returns the seeds that lead directly to each seed after one frame, in ascending order,
+    /// indexed by seed.
+    pub fn predecessor_map(&self) -> Vec<Vec<u16>> {
+        let mut predecessors = vec![Vec::new(); 0x10000];
+        for seed in 0..=0xFFFFu16 {
+            let mut rng = self.with_seed(seed);
+            rng.frame_advance();
+            predecessors[rng.seed as usize].push(seed);
+        }
+        predecessors
+    }
+
+    /// Returns the number of frames until the seed is equal to `target`, or `None` if the seed
+    /// enters a loop without reaching `target`.
+    pub fn distance_to(&self, target: u16) -> Option<usize> {
+        self.seeds_until_loop().position(|seed| seed == target)
+    }
+
+    /// Advances frames until `pred` holds for the current seed, returning the number of frames
+    /// advanced. Returns `None` if the seed enters a loop without satisfying `pred`; the seed is
+    /// left at the first repeated seed in that case.
+    ///
+    /// This generalizes `distance_to`.
+    pub fn advance_until(&mut self, pred: impl Fn(u16) -> bool) -> Option<u64> {
+        let mut seen = HashSet::new();
+        let mut frames = 0;
+        while !pred(self.seed) {
+            if !seen.insert(self.seed) {
+                return None;
+            }
+            self.frame_advance();
+            frames += 1;
+        }
+        Some(frames)
+    }
+
+    /// Returns the number of distinct seeds between the current state and the first repeated seed.
+    ///
+    /// Equivalent to `self.seeds_until_loop().count()`, but uses `cycle_of` rather than
+    /// remembering every seed.
+    pub fn prefix_len(&self) -> usize {
+        let (tail, period) = self.cycle_of(self.seed);
+        (tail + period) as usize
+    }
+
+    /// Returns the named RNG state matching `name`, or `None` if there is none.
+    ///
+    /// The name can be 'reset' (or anything starting with 'power'), 'beetom', 'sidehopper' (or
+    /// 'hopper'), or 'polyp', ignoring case.
+    pub fn from_name(name: &str) -> Option<Rng> {
+        match name.to_lowercase().as_str() {
+            "reset" => Some(Rng::RESET),
+            s if s.starts_with("power") => Some(Rng::RESET),
+
+            "beetom" => Some(Rng::BEETOM),
+            "sidehopper" | "hopper" => Some(Rng::SIDEHOPPER),
+            "polyp" => Some(Rng::POLYP),
+
+            _ => None,
+        }
+    }
+
+    /// Switches to the named RNG state matching `name` (see `from_name`), keeping the current
+    /// `calls_per_frame` and RNG parameters. Returns `false` without changing anything if there is
+    /// no such state.
+    ///
+    /// Like the named states themselves, this sets `xba` to true for 'polyp'.
+    pub fn reset_to_named(&mut self, name: &str) -> bool {
+        match Rng::from_name(name) {
+            Some(named) => {
+                self.seed = named.seed;
+                self.xba = named.xba;
+                true
+            }
+            None => false,
+        }
+    }
+
     /// The RNG state after reset.
     pub const RESET: Rng = Rng {
         seed: 0x0061,
         xba: false,
         calls_per_frame: 1,
+        params: RngParams::VANILLA,
     };
 
     /// The RNG state after entering a room with a beetom.
@@ -116,3 +458,23 @@ impl Rng {
         ..Rng::RESET
     };
 }
+
+impl FromStr for Rng {
+    type Err = ParseIntError;
+
+    /// Parses an RNG state from a seed specifier.
+    ///
+    /// The specifier can be 'reset' (or anything starting with 'power'), 'beetom', 'sidehopper'
+    /// (or 'hopper'), 'polyp', a hexadecimal number prefixed with '0x', or a decimal number.
+    /// Numeric seeds use the parameters of `Rng::RESET`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(rng) = Rng::from_name(s) {
+            return Ok(rng);
+        }
+
+        Ok(Rng {
+            seed: parse_seed(s)?,
+            ..Rng::RESET
+        })
+    }
+}
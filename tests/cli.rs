@@ -0,0 +1,557 @@
+use std::process::Output;
+
+use assert_cmd::Command;
+use predicates::prelude::*;
+use smrng::{
+    drops::{self, analysis, DropSet},
+    loop_analysis::{Analysis, SeedInfo},
+    Rng,
+};
+
+fn cmd(args: &[&str]) -> Command {
+    let mut cmd = Command::cargo_bin("smrng").unwrap();
+    cmd.args(args);
+    cmd
+}
+
+fn smrng(args: &[&str]) -> Output {
+    cmd(args).assert().success().get_output().clone()
+}
+
+fn json(args: &[&str]) -> serde_json::Value {
+    serde_json::from_slice(&smrng(args).stdout).unwrap()
+}
+
+#[test]
+fn progress_does_not_affect_json() {
+    for args in [
+        ["drops", "metroid", "--all-seeds", "--json"],
+        ["drops", "metroid", "--all-seeds", "--histogram"],
+    ] {
+        let plain = smrng(&args);
+        let mut with_progress = args.to_vec();
+        with_progress.push("--progress");
+        let with_progress = smrng(&with_progress);
+
+        assert_eq!(plain.stdout, with_progress.stdout);
+    }
+
+    let output = smrng(&["drops", "metroid", "--all-seeds", "--json", "--progress"]);
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn period_matches_analysis() {
+    for (flags, rng) in [
+        (&[][..], Rng::RESET),
+        (&["-i", "polyp"][..], Rng::POLYP),
+        (&["-n", "2"][..], Rng::RESET.with_calls_per_frame(2)),
+    ] {
+        let analysis = rng.analyze();
+        for seed in [rng.seed, 0, 0x1234, 0xffff] {
+            let mut args = flags.to_vec();
+            let target = seed.to_string();
+            args.extend(["period", &target, "--json"]);
+            let output = json(&args);
+
+            let l = &analysis.loops[analysis.loop_id(seed).unwrap() as usize];
+            assert_eq!(output["seed"], seed);
+            assert_eq!(output["period"], l.seeds.len(), "{args:?}");
+            assert_eq!(
+                output["in_loop"],
+                matches!(analysis[seed], SeedInfo::Loop { .. }),
+                "{args:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn count_range_matches_single_counts() {
+    let output = smrng(&["drops", "metroid", "--count-range", "3"]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    // Two header lines, then one row per count.
+    assert_eq!(stdout.lines().count(), 2 + 3);
+
+    let range = json(&["drops", "metroid", "--count-range", "3", "--json"]);
+    let range = range.as_array().unwrap();
+    assert_eq!(range.len(), 3);
+
+    let single = json(&["drops", "metroid", "--count", "1", "--json"]);
+    let mut first = range[0].clone();
+    assert_eq!(
+        first.as_object_mut().unwrap().remove("count"),
+        Some(1.into())
+    );
+    assert_eq!(first, single);
+}
+
+#[test]
+fn graph_outputs_successors() {
+    let successors: Vec<u16> = serde_json::from_value(json(&["graph", "--json"])).unwrap();
+    assert_eq!(successors.len(), 0x10000);
+    assert_eq!(successors, Rng::RESET.successor_map());
+
+    let mut rng = Rng::RESET.with_seed(0x61);
+    rng.frame_advance();
+    assert_eq!(successors[0x61], rng.seed);
+}
+
+#[test]
+fn drops_over_seed_range() {
+    let per_seed = json(&[
+        "drops",
+        "gamet",
+        "--seed-range",
+        "0x10..0x12",
+        "--per-seed",
+        "--json",
+    ]);
+    let seeds: Vec<_> = per_seed
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| entry["seed"].as_u64().unwrap())
+        .collect();
+    assert_eq!(seeds, [0x10, 0x11]);
+
+    let analysis = json(&["drops", "gamet", "--seed-range", "0x10..=0x11", "--json"]);
+    assert_eq!(analysis["seeds"], 2);
+}
+
+#[test]
+fn loops_cover_every_seed() {
+    // A few RNG calls per frame keeps the number of loops and branches small.
+    let analysis = json(&["-n", "3", "loops", "--json"]);
+    let seeds = analysis["seeds"].as_array().unwrap();
+    assert_eq!(seeds.len(), 0x10000);
+    assert!(seeds.iter().all(|seed| seed.is_i64()));
+
+    let count = |key: &str| -> usize {
+        analysis[key]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|entry| entry["seeds"].as_array().unwrap().len())
+            .sum()
+    };
+    assert_eq!(count("loops") + count("branches"), 0x10000);
+}
+
+#[test]
+fn drops_json_shape() {
+    let analysis = json(&["drops", "gamet", "--json"]);
+    let fields = [
+        "seeds",
+        "nothing",
+        "small_energy",
+        "big_energy",
+        "missile",
+        "super_missile",
+        "power_bomb",
+    ];
+    assert_eq!(analysis.as_object().unwrap().len(), fields.len());
+    for field in fields {
+        assert!(analysis[field].is_u64(), "{field}");
+    }
+    assert_eq!(analysis["seeds"], 2280);
+
+    // A gamet generates exactly one drop per kill.
+    let total: u64 = fields[1..]
+        .iter()
+        .map(|field| analysis[field].as_u64().unwrap())
+        .sum();
+    assert_eq!(total, 2280);
+}
+
+#[test]
+fn dump_loop_is_a_cycle() {
+    let seeds: Vec<u16> = serde_json::from_value(json(&["dump", "--loop", "--json"])).unwrap();
+    assert_eq!(seeds.len(), 2280);
+
+    let mut rng = Rng::RESET.with_seed(*seeds.last().unwrap());
+    rng.frame_advance();
+    assert_eq!(rng.seed, seeds[0]);
+}
+
+#[test]
+fn unknown_enemy_fails() {
+    cmd(&["drops", "notAnEnemy"])
+        .assert()
+        .code(2)
+        .stdout(predicate::str::is_empty())
+        .stderr(predicate::str::contains("notAnEnemy"));
+}
+
+#[test]
+fn route_threads_rng_between_enemies() {
+    let geega = drops::lookup_enemy("geega").unwrap();
+    let ripper = drops::lookup_enemy("ripper").unwrap();
+    let seeds = Rng::RESET.analyze().loops.swap_remove(0).seeds;
+
+    let route = json(&["route", "geega:2", "ripper:1", "--json"]);
+    let expected =
+        analysis::analyze_route(&[(geega, 2), (ripper, 1)], &DropSet::ALL, Rng::RESET, seeds)
+            .unwrap();
+    assert_eq!(route, serde_json::to_value(&expected).unwrap());
+    assert_eq!(
+        expected.total_drops(),
+        expected.seeds * (2 * geega.num_drops() + ripper.num_drops())
+    );
+
+    let reversed = json(&["route", "ripper:1", "geega:2", "--json"]);
+    assert_eq!(reversed["seeds"], route["seeds"]);
+    assert_ne!(reversed, route);
+}
+
+#[test]
+fn compare_enemy_with_itself() {
+    let output = json(&["drops", "metroid", "--compare", "metroid", "--json"]);
+    assert_eq!(output["enemy_a"], output["enemy_b"]);
+    assert_eq!(output["enemy_a"], json(&["drops", "metroid", "--json"]));
+
+    let delta = output["delta"].as_object().unwrap();
+    assert_eq!(delta.len(), 6);
+    for (field, value) in delta {
+        assert_eq!(value.as_f64(), Some(0.), "{field}");
+    }
+}
+
+#[test]
+fn json_lines_output() {
+    let output = smrng(&["dump", "--frames", "5", "--jsonl"]);
+    let lines: Vec<u16> = String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let expected: Vec<u16> =
+        serde_json::from_value(json(&["dump", "--frames", "5", "--json"])).unwrap();
+    assert_eq!(lines, expected);
+
+    let output = smrng(&["drops", "gamet", "--histogram", "--jsonl"]);
+    let rows: Vec<serde_json::Value> = String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(
+        serde_json::Value::Array(rows),
+        json(&["drops", "gamet", "--histogram", "--json"])
+    );
+}
+
+#[test]
+fn sampling_is_reproducible() {
+    let args = [
+        "drops",
+        "metroid",
+        "-a",
+        "--sample",
+        "500",
+        "--per-seed",
+        "--json",
+    ];
+    let first = json(&args);
+    assert_eq!(first.as_array().unwrap().len(), 500);
+    assert_eq!(first, json(&args));
+
+    let mut reseeded = args.to_vec();
+    reseeded.extend(["--rng-sample-seed", "1"]);
+    assert_ne!(first, json(&reseeded));
+
+    // A sample at least as large as the seed set analyzes every seed.
+    assert_eq!(
+        json(&["drops", "metroid", "--sample", "5000", "--json"]),
+        json(&["drops", "metroid", "--json"])
+    );
+}
+
+#[test]
+fn single_value_calls_range_matches_plain_run() {
+    let range = json(&["loops", "--calls-range", "2..=2", "--json"]);
+    let range = range.as_array().unwrap();
+    assert_eq!(range.len(), 1);
+    assert_eq!(range[0]["calls_per_frame"], 2);
+
+    let analysis: Analysis = serde_json::from_value(json(&["-n", "2", "loops", "--json"])).unwrap();
+    assert_eq!(
+        range[0]["stats"],
+        serde_json::to_value(analysis.stats()).unwrap()
+    );
+    assert_eq!(
+        range[0]["start_loop_period"],
+        analysis.start_loop().period()
+    );
+
+    let range = json(&["drops", "metroid", "--calls-range", "2..3", "--json"]);
+    let range = range.as_array().unwrap();
+    assert_eq!(range.len(), 1);
+    let mut entry = range[0].clone();
+    assert_eq!(
+        entry.as_object_mut().unwrap().remove("calls_per_frame"),
+        Some(2.into())
+    );
+    assert_eq!(entry, json(&["-n", "2", "drops", "metroid", "--json"]));
+}
+
+#[test]
+fn best_reachable_seed_produces_the_most_drops() {
+    let best = json(&[
+        "drops",
+        "geega",
+        "-c",
+        "4",
+        "--best-reachable",
+        "50",
+        "--json",
+    ]);
+    let seed = best["seed"].as_u64().unwrap() as u16;
+    assert_eq!(
+        Rng::RESET.distance_to(seed),
+        Some(best["frames"].as_u64().unwrap() as usize)
+    );
+
+    let geega = drops::lookup_enemy("geega").unwrap();
+    let drops = |seed| {
+        let analysis = analysis::analyze_correlated(
+            geega,
+            &DropSet::ALL,
+            4,
+            Rng::RESET,
+            std::iter::once(seed),
+        )
+        .unwrap();
+        analysis.total_drops() - analysis.nothing
+    };
+    let most = Rng::RESET.seeds_until_loop().take(51).map(drops).max();
+    assert_eq!(Some(drops(seed)), most);
+}
+
+#[test]
+fn probabilities_sum_to_one() {
+    for args in [
+        ["drops", "gamet", "-c", "1"],
+        ["drops", "gamet", "-c", "3"],
+        ["drops", "metroid", "-c", "1"],
+    ] {
+        let mut args = args.to_vec();
+        args.extend(["--probabilities", "--json"]);
+        let probabilities = json(&args);
+        let probabilities = probabilities.as_object().unwrap();
+        assert_eq!(probabilities.len(), 6);
+        let sum: f64 = probabilities.values().map(|p| p.as_f64().unwrap()).sum();
+        assert!((sum - 1.).abs() < 1e-4, "{args:?}: {sum}");
+    }
+
+    // With one drop per kill, each probability is the mean drops per kill.
+    let rates = json(&["drops", "gamet", "-c", "3", "--json"]);
+    let probabilities = json(&["drops", "gamet", "-c", "3", "--probabilities", "--json"]);
+    let seeds = rates["seeds"].as_f64().unwrap();
+    let missile = rates["missile"].as_f64().unwrap() / (seeds * 3.);
+    assert!((probabilities["missile"].as_f64().unwrap() - missile).abs() < 1e-6);
+}
+
+#[test]
+fn between_seeds() {
+    let to = Rng::RESET.seed_after(5);
+    let seeds: Vec<u16> =
+        serde_json::from_value(json(&["between", "0x0061", &to.to_string(), "--json"])).unwrap();
+    let expected: Vec<u16> = Rng::RESET.seeds_until_loop().take(6).collect();
+    assert_eq!(seeds, expected);
+
+    let single: Vec<u16> =
+        serde_json::from_value(json(&["between", "0x0061", "0x0061", "--json"])).unwrap();
+    assert_eq!(single, [0x0061]);
+
+    // The reset seed never reaches a different loop.
+    let unreachable = Rng::RESET.analyze().loops[2].start().to_string();
+    cmd(&["between", "0x0061", &unreachable])
+        .assert()
+        .code(2)
+        .stdout(predicate::str::is_empty())
+        .stderr(predicate::str::contains("not reachable"));
+}
+
+#[test]
+fn bash_completions() {
+    let output = smrng(&["completions", "bash"]);
+    let script = String::from_utf8(output.stdout).unwrap();
+    assert!(!script.is_empty());
+    assert!(script.contains("smrng"));
+    assert!(script.contains("--calls-per-frame"));
+}
+
+#[test]
+fn histogram_top_and_min_seeds() {
+    let full = json(&["drops", "metroid", "--histogram", "--json"]);
+    let full = full.as_array().unwrap();
+    assert!(full.len() > 3);
+
+    let top = json(&["drops", "metroid", "--histogram", "--top", "3", "--json"]);
+    assert_eq!(top.as_array().unwrap()[..], full[..3]);
+    let seeds = |entry: &serde_json::Value| entry["seeds"].as_u64().unwrap();
+    let least_common_top = seeds(&top[2]);
+    assert!(full[3..]
+        .iter()
+        .all(|entry| seeds(entry) <= least_common_top));
+
+    let common = json(&[
+        "drops",
+        "metroid",
+        "--histogram",
+        "--min-seeds",
+        "50",
+        "--json",
+    ]);
+    let expected: Vec<_> = full
+        .iter()
+        .filter(|entry| seeds(entry) >= 50)
+        .cloned()
+        .collect();
+    assert!(!expected.is_empty() && expected.len() < full.len());
+    assert_eq!(common.as_array().unwrap(), &expected);
+}
+
+#[test]
+fn histogram_sort_by_field() {
+    let histogram = json(&[
+        "drops",
+        "metroid",
+        "--histogram",
+        "--sort",
+        "super",
+        "--json",
+    ]);
+    let supers: Vec<u64> = histogram
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| entry["super_missile"].as_u64().unwrap())
+        .collect();
+    assert!(supers.len() > 1);
+    assert!(supers.windows(2).all(|pair| pair[0] >= pair[1]));
+    assert!(supers[0] > *supers.last().unwrap());
+}
+
+#[test]
+fn dump_reverse_walks_predecessors() {
+    let seeds: Vec<u16> = serde_json::from_value(json(&["dump", "--reverse", "--json"])).unwrap();
+    assert!(seeds.len() > 1);
+    assert_eq!(seeds[0], Rng::RESET.seed);
+
+    for pair in seeds.windows(2) {
+        let mut rng = Rng::RESET.with_seed(pair[1]);
+        rng.frame_advance();
+        assert_eq!(rng.seed, pair[0]);
+
+        // The smallest predecessor is the one followed.
+        assert!((0..pair[1]).all(|seed| {
+            let mut rng = Rng::RESET.with_seed(seed);
+            rng.frame_advance();
+            rng.seed != pair[0]
+        }));
+    }
+
+    // The walk stops at a seed with no predecessors.
+    let first = *seeds.last().unwrap();
+    assert!((0..=0xFFFF).all(|seed| {
+        let mut rng = Rng::RESET.with_seed(seed);
+        rng.frame_advance();
+        rng.seed != first
+    }));
+}
+
+#[test]
+fn loops_color() {
+    let never = smrng(&["loops", "--color", "never"]).stdout;
+    assert!(!String::from_utf8(never).unwrap().contains("\x1b["));
+
+    let always = smrng(&["loops", "--color", "always"]).stdout;
+    assert!(String::from_utf8(always).unwrap().contains("\x1b["));
+
+    // Color is ignored for JSON output.
+    let json = smrng(&["loops", "--color", "always", "--json"]).stdout;
+    assert!(!String::from_utf8(json).unwrap().contains("\x1b["));
+}
+
+#[test]
+fn drops_audit() {
+    assert_eq!(json(&["drops", "audit", "--json"]), serde_json::json!([]));
+    let output = String::from_utf8(smrng(&["drops", "audit"]).stdout).unwrap();
+    assert!(output.contains("sum to 255"));
+
+    // `audit` takes no enemy, and an enemy is still required without it.
+    cmd(&["drops", "audit", "metroid"]).assert().failure();
+    cmd(&["drops"]).assert().failure();
+}
+
+#[test]
+fn streamed_dump_matches_buffered_json() {
+    let streamed = smrng(&["dump", "--loop", "--json"]).stdout;
+    let buffered = serde_json::to_vec(&Rng::RESET.analyze().loops[0].seeds).unwrap();
+    assert_eq!(streamed, buffered);
+
+    let streamed = smrng(&["dump", "--frames", "0", "--json"]).stdout;
+    assert_eq!(streamed, b"[]");
+}
+
+#[test]
+fn markdown_tables() {
+    for args in [
+        &["drops", "gamet", "--markdown"][..],
+        &["drops", "gamet", "--histogram", "--markdown"],
+        &["loops", "--markdown"],
+    ] {
+        let output = String::from_utf8(smrng(args).stdout).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        let tables: Vec<&[&str]> = lines
+            .split(|line| !line.starts_with('|'))
+            .filter(|table| !table.is_empty())
+            .collect();
+        assert!(!tables.is_empty(), "{args:?}");
+
+        for table in tables {
+            let columns = |row: &str| row.matches('|').count();
+            assert!(table.len() >= 2, "{args:?}: {table:?}");
+            let separator = table[1];
+            assert!(
+                separator
+                    .trim_matches('|')
+                    .split('|')
+                    .all(|cell| cell.contains('-') && cell.trim_matches([':', '-']).is_empty()),
+                "{args:?}: {separator}"
+            );
+            assert!(
+                table.iter().all(|row| columns(row) == columns(table[0])),
+                "{args:?}: {table:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn explain_skips_seed_selection() {
+    for args in [
+        &["drops", "gamet", "--explain"][..],
+        &["drops", "gamet", "--best-reachable", "10"],
+        &["drops", "gamet", "--ideal"],
+    ] {
+        let output = smrng(args);
+        assert!(!output.stdout.is_empty(), "{args:?}");
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(!stderr.contains("Using"), "{args:?}: {stderr}");
+    }
+}
+
+#[test]
+fn seed_arguments_accept_uppercase_hex() {
+    assert_eq!(
+        json(&["period", "0X61", "--json"]),
+        json(&["period", "0x0061", "--json"])
+    );
+    assert_eq!(
+        json(&["between", "0X61", "0X61", "--json"]),
+        serde_json::json!([0x61])
+    );
+}
@@ -0,0 +1,1019 @@
+use smrng::{
+    drops::{self, analysis, Drop, DropError, DropSet, DropTable, ExplosionDrop, RngCost},
+    Rng,
+};
+
+#[test]
+fn conditional_drops_exclude_nothing() {
+    let pirate = drops::lookup_enemy("spacePirateYellowWall").unwrap();
+
+    let unconditional = pirate.ideal_drops_per_farm(Drop::SuperMissile, &DropSet::ALL, 1);
+    let conditional = pirate.conditional_drops_per_farm(Drop::SuperMissile, &DropSet::ALL, 1);
+    assert!((unconditional - 5. / 255.).abs() < 1e-6);
+    assert!((conditional - 5. / 60.).abs() < 1e-6);
+
+    let total: f32 = DropSet::ALL
+        .iter()
+        .map(|d| pirate.conditional_drops_per_farm(d, &DropSet::ALL, 1))
+        .sum();
+    assert!((total - 1.).abs() < 1e-6);
+    assert_eq!(
+        pirate.conditional_drops_per_farm(Drop::Nothing, &DropSet::ALL, 1),
+        0.
+    );
+}
+
+#[test]
+fn conditional_drops_with_only_nothing() {
+    let rinka = drops::lookup_enemy("rinka").unwrap();
+    for drop in DropSet::ALL {
+        assert_eq!(rinka.conditional_drops_per_farm(drop, &DropSet::ALL, 1), 0.);
+    }
+}
+
+#[test]
+fn roll_multiple_counted_reports_cost() {
+    let gamet = drops::lookup_enemy("gamet").unwrap();
+    let mut rng = Rng::RESET;
+    let (drops, cost) = gamet.roll_multiple_counted(&mut rng, &DropSet::ALL, 1);
+    assert_eq!(drops.len(), 1);
+    assert_eq!(
+        cost,
+        RngCost {
+            rolls: 2,
+            frame_advances: 0,
+            zero_rerolls: 0,
+        }
+    );
+
+    // Five main drops, then five explosions of two rolls each eight frames apart, then the extra
+    // drop.
+    let metroid = drops::lookup_enemy("metroid").unwrap();
+    let mut counted_rng = Rng::RESET;
+    let (drops, cost) = metroid.roll_multiple_counted(&mut counted_rng, &DropSet::ALL, 1);
+    assert_eq!(drops.len(), 6);
+    assert_eq!(cost.rolls, 5 * 2 + 5 * 2 + 1);
+    assert_eq!(cost.frame_advances, 5 * 8);
+
+    let mut rng = Rng::RESET;
+    let uncounted: Vec<_> = metroid.roll_multiple(&mut rng, &DropSet::ALL, 1).collect();
+    assert_eq!(drops, uncounted);
+    assert_eq!(rng.seed, counted_rng.seed);
+}
+
+#[test]
+fn roll_one_explained_matches_roll_one() {
+    let metroid = drops::lookup_enemy("metroid").unwrap();
+    for seed in (0..=0xFFFFu16).step_by(97) {
+        let mut rng = Rng::RESET.with_seed(seed);
+        let mut explained_rng = rng.clone();
+        let drop = metroid.roll_one(&mut rng, &DropSet::ALL);
+        let (explained, explanation) = metroid
+            .roll_one_explained(&mut explained_rng, &DropSet::ALL)
+            .unwrap();
+
+        assert_eq!(drop, explained);
+        assert_eq!(rng.seed, explained_rng.seed);
+        assert_eq!(*explanation.rolls.last().unwrap(), rng.seed);
+        assert_eq!(explanation.thresholds.last().unwrap().1, 255);
+    }
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn par_histogram_matches_histogram() {
+    let metroid = drops::lookup_enemy("metroid").unwrap();
+    let seeds: Vec<u16> = (0..=0xFFFF).step_by(7).collect();
+    let counted = DropSet::ALL.difference(&DropSet::from_iter([Drop::Nothing]));
+
+    let serial = analysis::histogram(
+        metroid,
+        &DropSet::ALL,
+        1,
+        Rng::RESET,
+        seeds.iter().copied(),
+        &counted,
+    )
+    .unwrap();
+    let parallel =
+        analysis::par_histogram(metroid, &DropSet::ALL, 1, Rng::RESET, &seeds, &counted).unwrap();
+    assert_eq!(serial, parallel);
+}
+
+#[test]
+fn drop_set_complement_and_subsets() {
+    assert_eq!(DropSet::EMPTY.complement(), DropSet::ALL);
+    assert_eq!(DropSet::ALL.complement(), DropSet::EMPTY);
+    assert_eq!(DropSet::MINOR.complement(), DropSet::MAJOR);
+    assert_eq!(DropSet::MAJOR.complement().complement(), DropSet::MAJOR);
+
+    assert!(DropSet::HEALTH_BOMB.is_subset(&DropSet::MINOR));
+    assert!(!DropSet::HEALTH_BOMB.is_subset(&DropSet::MAJOR));
+    assert!(DropSet::MINOR.is_superset(&DropSet::HEALTH_BOMB));
+    assert!(!DropSet::HEALTH_BOMB.is_superset(&DropSet::MINOR));
+    assert!(DropSet::EMPTY.is_subset(&DropSet::EMPTY));
+    assert!(DropSet::ALL.is_superset(&DropSet::ALL));
+}
+
+#[test]
+fn drop_analysis_rates() {
+    let metroid = drops::lookup_enemy("metroid").unwrap();
+    let analysis =
+        analysis::analyze_correlated(metroid, &DropSet::ALL, 1, Rng::RESET, 0..100).unwrap();
+
+    // A metroid always generates six drops.
+    assert_eq!(analysis.total_drops(), 600);
+    let total: f32 = DropSet::ALL.iter().map(|d| analysis.rate(d)).sum();
+    assert!((total - 6.).abs() < 1e-4);
+    assert_eq!(analysis.rate(Drop::Missile), analysis.missile as f32 / 100.);
+
+    let empty = analysis::DropAnalysis::default();
+    assert_eq!(empty.total_drops(), 0);
+    for drop in DropSet::ALL {
+        assert_eq!(empty.rate(drop), 0.);
+    }
+}
+
+#[test]
+fn custom_explosion_cost() {
+    let tables = drops::load_enemy_drops(
+        r#"{
+            "exploder": {
+                "nothing": 255,
+                "small_energy": 0,
+                "big_energy": 0,
+                "missile": 0,
+                "super_missile": 0,
+                "power_bomb": 0,
+                "extra": {
+                    "custom": {
+                        "num_explosions": 3,
+                        "rng_per_explosion": 4,
+                        "explosion_interval": 10
+                    }
+                }
+            }
+        }"#,
+    )
+    .unwrap();
+    let exploder = &tables["exploder"];
+
+    let mut rng = Rng::RESET;
+    let (drops, cost) = exploder.roll_multiple_counted(&mut rng, &DropSet::ALL, 2);
+    assert_eq!(drops, [Drop::Nothing; 4]);
+    assert_eq!(cost.frame_advances, 3 * 10);
+    // Two main drops of at least two rolls each, the explosions, then two extra drops.
+    assert!(cost.rolls >= 2 * 2 + 3 * 4 * 2 + 2);
+}
+
+#[test]
+fn drops_per_frame_scales_with_kill_time() {
+    let metroid = drops::lookup_enemy("metroid").unwrap();
+    for drop in DropSet::ALL {
+        let fast = metroid.drops_per_frame(drop, &DropSet::ALL, 1, 60);
+        let slow = metroid.drops_per_frame(drop, &DropSet::ALL, 1, 120);
+        assert!((fast - 2. * slow).abs() < 1e-6);
+        assert!((fast * 60. - metroid.ideal_drops_per_farm(drop, &DropSet::ALL, 1)).abs() < 1e-5);
+    }
+}
+
+#[test]
+fn parse_drop_names() {
+    let cases = [
+        ("nothing", Drop::Nothing),
+        ("small_energy", Drop::SmallEnergy),
+        ("small", Drop::SmallEnergy),
+        ("big_energy", Drop::BigEnergy),
+        ("big", Drop::BigEnergy),
+        ("missile", Drop::Missile),
+        ("super_missile", Drop::SuperMissile),
+        ("super", Drop::SuperMissile),
+        ("power_bomb", Drop::PowerBomb),
+        ("pb", Drop::PowerBomb),
+        ("PB", Drop::PowerBomb),
+        ("Super_Missile", Drop::SuperMissile),
+    ];
+    for (name, drop) in cases {
+        assert_eq!(name.parse::<Drop>(), Ok(drop), "{name}");
+    }
+
+    let err = "rocket".parse::<Drop>().unwrap_err();
+    assert!(err.contains("rocket"));
+}
+
+#[test]
+fn serialize_drop_table() {
+    let metroid = drops::lookup_enemy("metroid").unwrap();
+    let json = serde_json::to_value(metroid).unwrap();
+    for (field, chance) in [
+        ("nothing", 10),
+        ("small_energy", 25),
+        ("big_energy", 50),
+        ("missile", 90),
+        ("super_missile", 50),
+        ("power_bomb", 30),
+    ] {
+        assert_eq!(json[field], chance, "{field}");
+    }
+    assert_eq!(json["count"], 5);
+    assert_eq!(json["extra"], "metroid");
+}
+
+#[test]
+fn weighted_analysis_on_a_loop_is_unweighted() {
+    let analysis = Rng::RESET.analyze();
+    let visits = analysis.visit_counts();
+    let metroid = drops::lookup_enemy("metroid").unwrap();
+
+    for l in &analysis.loops {
+        let unweighted = analysis::analyze_correlated(
+            metroid,
+            &DropSet::ALL,
+            1,
+            Rng::RESET,
+            l.seeds.iter().copied(),
+        )
+        .unwrap();
+        let weighted = analysis::analyze_weighted(
+            metroid,
+            &DropSet::ALL,
+            1,
+            Rng::RESET,
+            l.seeds
+                .iter()
+                .map(|&seed| (seed, visits[seed as usize] as f64)),
+        )
+        .unwrap();
+
+        for drop in DropSet::ALL {
+            assert!((weighted.get(drop) - unweighted.rate(drop) as f64).abs() < 1e-5);
+        }
+    }
+}
+
+#[test]
+fn drop_set_bits() {
+    for bits in 0..=u8::MAX {
+        match DropSet::from_bits(bits) {
+            Some(set) => {
+                assert!(bits < 0x40);
+                assert_eq!(set.bits(), bits);
+                assert_eq!(DropSet::from_bits(set.bits()), Some(set.clone()));
+
+                let json = serde_json::to_string(&set).unwrap();
+                assert_eq!(json, bits.to_string());
+                assert_eq!(serde_json::from_str::<DropSet>(&json).unwrap(), set);
+            }
+            None => {
+                assert!(bits >= 0x40);
+                assert!(serde_json::from_str::<DropSet>(&bits.to_string()).is_err());
+            }
+        }
+    }
+    assert_eq!(DropSet::ALL.bits(), 0x3f);
+}
+
+#[test]
+fn sample_size_warning() {
+    let metroid = drops::lookup_enemy("metroid").unwrap();
+    let small = analysis::analyze_correlated(metroid, &DropSet::ALL, 1, Rng::RESET, 0..5).unwrap();
+    assert!(small.sample_size_warning().unwrap().contains('5'));
+
+    let full =
+        analysis::analyze_correlated(metroid, &DropSet::ALL, 1, Rng::RESET, 0..=0xFFFF).unwrap();
+    assert_eq!(full.sample_size_warning(), None);
+}
+
+#[test]
+fn expected_drops_all_matches_individual_calls() {
+    let possible_sets = [DropSet::ALL, DropSet::MINOR, DropSet::MAJOR, DropSet::EMPTY];
+    for enemy in ["metroid", "gamet", "rinka", "spacePirateYellowWall"] {
+        let table = drops::lookup_enemy(enemy).unwrap();
+        for possible in &possible_sets {
+            let all = table.expected_drops_all(possible, 3);
+            for drop in DropSet::ALL {
+                assert_eq!(
+                    all.get(drop) as f32,
+                    table.ideal_drops_per_farm(drop, possible, 3),
+                    "{enemy} {drop:?}"
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn correlated_matches_ideal_over_all_seeds() {
+    let no_missiles = DropSet::ALL.difference(&DropSet::from_iter([Drop::Missile]));
+    for enemy in ["gamet", "spacePirateYellowWall", "sidehopper", "zebbo"] {
+        let table = drops::lookup_enemy(enemy).unwrap();
+        for possible in [DropSet::ALL, no_missiles.clone(), DropSet::MAJOR] {
+            let correlated =
+                analysis::analyze_correlated(table, &possible, 1, Rng::RESET, 0..=0xFFFF).unwrap();
+            let ideal = table.expected_drops_all(&possible, 1);
+            for drop in DropSet::ALL {
+                let difference = correlated.rate(drop) as f64 - ideal.get(drop);
+                assert!(difference.abs() < 0.01, "{enemy} {possible:?} {drop:?}");
+            }
+        }
+    }
+}
+
+#[test]
+fn add_drop_analyses() {
+    let metroid = drops::lookup_enemy("metroid").unwrap();
+    let analyze = |seeds: std::ops::Range<u16>| {
+        analysis::analyze_correlated(metroid, &DropSet::ALL, 1, Rng::RESET, seeds).unwrap()
+    };
+
+    let whole = analyze(0..1000);
+    let mut halves = analyze(0..400) + analyze(400..1000);
+    assert_eq!(halves, whole);
+
+    halves += analysis::DropAnalysis::default();
+    assert_eq!(halves, whole);
+    assert_eq!(analysis::DropAnalysis::default() + whole.clone(), whole);
+}
+
+#[test]
+fn continuous_analysis_carries_rng_state() {
+    let metroid = drops::lookup_enemy("metroid").unwrap();
+    let loop_seeds = &Rng::RESET.analyze().loops[0].seeds;
+    let kills = loop_seeds.len() as u32;
+    let start = Rng::RESET.with_seed(loop_seeds[0]);
+
+    let continuous =
+        analysis::analyze_continuous(metroid, &DropSet::ALL, 1, start.clone(), kills).unwrap();
+    let reset = analysis::analyze_correlated(
+        metroid,
+        &DropSet::ALL,
+        1,
+        Rng::RESET,
+        loop_seeds.iter().copied(),
+    )
+    .unwrap();
+    assert_eq!(continuous.seeds, reset.seeds);
+    assert_ne!(continuous, reset);
+
+    // Continuous farming is equivalent to resetting to the seed the game is at before each kill.
+    let mut rng = start;
+    let mut visited = Vec::new();
+    for _ in 0..kills {
+        visited.push(rng.seed);
+        metroid
+            .roll_multiple(&mut rng, &DropSet::ALL, 1)
+            .for_each(drop);
+        rng.frame_advance();
+    }
+    let replayed =
+        analysis::analyze_correlated(metroid, &DropSet::ALL, 1, Rng::RESET, visited).unwrap();
+    assert_eq!(continuous, replayed);
+}
+
+#[test]
+fn drop_set_iter_sorted() {
+    let sorted: Vec<_> = DropSet::ALL.iter_sorted().collect();
+    assert_eq!(
+        sorted,
+        [
+            Drop::SmallEnergy,
+            Drop::BigEnergy,
+            Drop::Missile,
+            Drop::SuperMissile,
+            Drop::PowerBomb,
+            Drop::Nothing,
+        ]
+    );
+
+    let set = DropSet::from_iter([Drop::Nothing, Drop::PowerBomb, Drop::SmallEnergy]);
+    let sorted: Vec<_> = set.iter_sorted().collect();
+    assert_eq!(sorted, [Drop::SmallEnergy, Drop::PowerBomb, Drop::Nothing]);
+    assert_eq!(DropSet::EMPTY.iter_sorted().count(), 0);
+}
+
+#[test]
+fn all_drops() {
+    let unique: std::collections::HashSet<_> = Drop::ALL.into_iter().collect();
+    assert_eq!(unique.len(), Drop::ALL.len());
+    for drop in [
+        Drop::Nothing,
+        Drop::SmallEnergy,
+        Drop::BigEnergy,
+        Drop::Missile,
+        Drop::SuperMissile,
+        Drop::PowerBomb,
+    ] {
+        assert!(Drop::ALL.contains(&drop), "{drop:?}");
+    }
+
+    // `DropSet::iter` yields drops in the same order.
+    assert!(DropSet::ALL.iter().eq(Drop::ALL));
+}
+
+#[test]
+fn normalized_chances_sum_to_one() {
+    for enemy in [
+        "metroid",
+        "gamet",
+        "rinka",
+        "zebbo",
+        "spacePirateYellowWall",
+    ] {
+        let table = drops::lookup_enemy(enemy).unwrap();
+        for possible in [DropSet::ALL, DropSet::MINOR, DropSet::MAJOR, DropSet::EMPTY] {
+            let chances = table.normalized_chances(&possible);
+            let total: f32 = chances.iter().sum();
+            assert!((total - 1.).abs() < 1e-5, "{enemy} {possible:?}");
+
+            for (drop, chance) in Drop::ALL.into_iter().zip(chances) {
+                assert!(chance >= 0., "{enemy} {possible:?} {drop:?}");
+                if drop != Drop::Nothing && !possible.contains(&drop) {
+                    assert_eq!(chance, 0., "{enemy} {possible:?} {drop:?}");
+                }
+            }
+        }
+    }
+
+    // A space pirate's super missile chance is 5 out of the 255 nonzero low bytes.
+    let pirate = drops::lookup_enemy("spacePirateYellowWall").unwrap();
+    let chances = pirate.normalized_chances(&DropSet::ALL);
+    assert!((chances[4] - 5. / 255.).abs() < 1e-6);
+}
+
+#[test]
+fn clone_drop_table() {
+    let metroid = drops::lookup_enemy("metroid").unwrap();
+    let clone = metroid.clone();
+    assert!(clone == *metroid);
+    assert!(clone != *drops::lookup_enemy("gamet").unwrap());
+
+    let mut modified = metroid.clone();
+    modified.extra = None;
+    assert!(modified != *metroid);
+}
+
+#[test]
+fn csv_row_matches_header() {
+    let metroid = drops::lookup_enemy("metroid").unwrap();
+    let analysis =
+        analysis::analyze_correlated(metroid, &DropSet::ALL, 2, Rng::RESET, 0..1000).unwrap();
+
+    let header: Vec<&str> = analysis::DropAnalysis::csv_header().split(',').collect();
+    let row = analysis.to_csv_row();
+    let row: Vec<&str> = row.split(',').collect();
+    assert_eq!(header.len(), row.len());
+
+    // The columns are named and ordered like the JSON fields.
+    let json = serde_json::to_value(&analysis).unwrap();
+    for (column, value) in header.iter().zip(&row) {
+        assert_eq!(json[column].to_string(), *value);
+    }
+}
+
+#[test]
+fn zero_low_byte_is_rerolled() {
+    // From 0x00aa, the roll after the discarded one has a low byte of zero, so the drop takes an
+    // extra roll.
+    let mut rng = Rng::RESET.with_seed(0x00aa);
+    assert_eq!(drops::zero_rerolls(&rng), 0);
+    rng.roll();
+    assert_eq!(drops::zero_rerolls(&rng), 1);
+
+    let gamet = drops::lookup_enemy("gamet").unwrap();
+    let mut rng = Rng::RESET.with_seed(0x00aa);
+    let (_, cost) = gamet.roll_multiple_counted(&mut rng, &DropSet::ALL, 1);
+    assert_eq!(
+        cost,
+        RngCost {
+            rolls: 3,
+            frame_advances: 0,
+            zero_rerolls: 1,
+        }
+    );
+    assert_eq!(
+        Some(&rng.seed),
+        Rng::RESET.with_seed(0x00aa).roll_n(3).last()
+    );
+
+    assert_eq!(drops::expected_zero_rerolls(&Rng::RESET), 257. / 65536.);
+}
+
+#[test]
+fn ammo_and_energy_sets() {
+    // Derived in a const context from the const combinators.
+    const RESOURCES: DropSet = DropSet::AMMO.union(&DropSet::ENERGY);
+
+    for drop in [Drop::Missile, Drop::SuperMissile, Drop::PowerBomb] {
+        assert!(DropSet::AMMO.contains(&drop));
+        assert!(!DropSet::ENERGY.contains(&drop));
+    }
+    for drop in [Drop::SmallEnergy, Drop::BigEnergy] {
+        assert!(DropSet::ENERGY.contains(&drop));
+        assert!(!DropSet::AMMO.contains(&drop));
+    }
+    assert_eq!(DropSet::ENERGY, DropSet::HEALTH_BOMB);
+    assert_eq!(
+        RESOURCES,
+        DropSet::ALL.difference(&"nothing".parse().unwrap())
+    );
+
+    assert_eq!("ammo".parse::<DropSet>().unwrap(), DropSet::AMMO);
+    assert_eq!("energy".parse::<DropSet>().unwrap(), DropSet::ENERGY);
+}
+
+#[test]
+fn validate_drop_tables() {
+    for (enemy, table) in drops::ENEMY_DROPS.iter() {
+        assert_eq!(table.validate(), Ok(()), "{enemy}");
+    }
+
+    let all_zero = DropTable::new(0, 0, 0, 0, 0, 0);
+    assert_eq!(all_zero.validate(), Err(DropError::AllZero));
+    assert!(!all_zero.is_valid());
+    // With no minor drop chances, every roll not claimed by a major drop produces nothing.
+    assert_eq!(DropTable::new(0, 0, 0, 0, 100, 0).validate(), Ok(()));
+    assert_eq!(
+        DropTable::new(100, 100, 100, 0, 0, 0).validate(),
+        Err(DropError::MinorOverflow(300))
+    );
+    assert_eq!(
+        DropTable::new(0, 0, 0, 0, 200, 100).validate(),
+        Err(DropError::MajorOverflow(300))
+    );
+    assert!(DropTable::new(255, 0, 0, 0, 0, 0).is_valid());
+
+    // Custom tables are validated when they are loaded or registered.
+    let loaded = drops::load_enemy_drops(
+        r#"{
+            "ghost": {
+                "nothing": 0,
+                "small_energy": 0,
+                "big_energy": 0,
+                "missile": 0,
+                "super_missile": 0,
+                "power_bomb": 0
+            }
+        }"#,
+    );
+    assert!(matches!(
+        loaded,
+        Err(drops::LoadError::InvalidTable { ref enemy, reason: DropError::AllZero })
+            if enemy == "ghost"
+    ));
+    assert_eq!(
+        drops::register_enemy("ghost".to_string(), all_zero),
+        Err(DropError::AllZero)
+    );
+    assert!(drops::lookup_enemy("ghost").is_none());
+}
+
+#[test]
+fn roll_with_every_minor_excluded() {
+    // Being full on energy and missiles leaves no minor drop with a nonzero chance, so the pooled
+    // minor chance is zero; this used to divide by zero.
+    let table = DropTable::new(0, 100, 0, 55, 60, 40);
+    let possible = DropSet::ALL - &DropSet::from_iter([Drop::SmallEnergy, Drop::Missile]);
+    assert_eq!(table.pooled_minor(&possible), 0);
+
+    for seed in (0..=0xFFFFu16).step_by(61) {
+        let rng = Rng::RESET.with_seed(seed);
+        let random = *rng
+            .clone()
+            .roll_n(1 + drops::zero_rerolls(&rng) as usize)
+            .last()
+            .unwrap();
+        let expected = match random & 0xFF {
+            1..=60 => Drop::SuperMissile,
+            61..=100 => Drop::PowerBomb,
+            _ => Drop::Nothing,
+        };
+        assert_eq!(
+            table.roll_one(&mut rng.clone(), &possible),
+            expected,
+            "{seed:#06x}"
+        );
+    }
+
+    let ideal = table.expected_drops_all(&possible, 1);
+    assert_eq!(ideal.small_energy + ideal.big_energy + ideal.missile, 0.);
+    assert!((ideal.super_missile - 60. / 255.).abs() < 1e-9);
+    assert!((ideal.power_bomb - 40. / 255.).abs() < 1e-9);
+    assert!((ideal.nothing - 155. / 255.).abs() < 1e-9);
+}
+
+/// A table that drops nothing if the low byte of its drop roll is below 128 and a super missile
+/// otherwise, so that its drops can be worked out by hand.
+const COIN_FLIP: DropTable = DropTable::new(127, 0, 0, 0, 128, 0);
+
+/// Seeds whose drops from `COIN_FLIP` were worked by hand with the reference values in
+/// `tests/rng.rs`. The drop routine discards one roll and then uses the next, so from $0061 the
+/// discarded roll is $02F6 and the drop roll is $0FDF, whose low byte is at least 128.
+const COIN_FLIP_SEEDS: [(u16, Drop); 5] = [
+    (0x0000, Drop::Nothing),      // $0111, $0666
+    (0x0061, Drop::SuperMissile), // $02F6, $0FDF
+    (0x0017, Drop::SuperMissile), // $0184, $08A5
+    (0x0025, Drop::Nothing),      // $01CA, $0A03
+    (0x0011, Drop::Nothing),      // $0166, $080F
+];
+
+#[test]
+fn chance_at_least_one() {
+    let seeds = COIN_FLIP_SEEDS.map(|(seed, _)| seed);
+    for (seed, drop) in COIN_FLIP_SEEDS {
+        let mut rng = Rng::RESET.with_seed(seed);
+        assert!(
+            COIN_FLIP.roll(&mut rng, &DropSet::ALL).eq([drop]),
+            "{seed:#06x}"
+        );
+    }
+
+    let chance = |drop, possible: &DropSet| {
+        analysis::chance_at_least_one(&COIN_FLIP, possible, 1, Rng::RESET, seeds, drop).unwrap()
+    };
+    assert_eq!(chance(Drop::SuperMissile, &DropSet::ALL), 2. / 5.);
+    assert_eq!(chance(Drop::Nothing, &DropSet::ALL), 3. / 5.);
+    assert_eq!(chance(Drop::PowerBomb, &DropSet::ALL), 0.);
+
+    // A drop that isn't possible never drops.
+    assert_eq!(chance(Drop::SuperMissile, &DropSet::MINOR), 0.);
+    assert_eq!(
+        analysis::chance_at_least_one(&COIN_FLIP, &DropSet::ALL, 1, Rng::RESET, [], Drop::Nothing)
+            .unwrap(),
+        0.
+    );
+}
+
+#[test]
+fn chance_nothing() {
+    let seeds = COIN_FLIP_SEEDS.map(|(seed, _)| seed);
+    let chance = analysis::chance_nothing(&COIN_FLIP, &DropSet::ALL, 1, Rng::RESET, seeds).unwrap();
+    assert_eq!(chance, 3. / 5.);
+
+    // A red space pirate drops nothing 195 times out of 255; killing two at once is a dud only if
+    // both drop nothing.
+    let pirate = drops::lookup_enemy("spacePirateRed").unwrap();
+    let seeds = &Rng::RESET.analyze().loops[0].seeds;
+    for n in [1, 2] {
+        let chance =
+            analysis::chance_nothing(pirate, &DropSet::ALL, n, Rng::RESET, seeds.iter().copied())
+                .unwrap();
+        let duds = seeds
+            .iter()
+            .filter(|&&seed| {
+                let mut rng = Rng::RESET.with_seed(seed);
+                let dud = pirate
+                    .roll_multiple(&mut rng, &DropSet::ALL, n)
+                    .all(|drop| drop == Drop::Nothing);
+                dud
+            })
+            .count();
+        assert!(chance > 0. && chance < 1., "{n}: {chance}");
+        assert_eq!(chance, duds as f32 / seeds.len() as f32, "{n}");
+        assert!(
+            (chance - (195f32 / 255.).powi(n as i32)).abs() < 0.05,
+            "{n}: {chance}"
+        );
+    }
+
+    let rinka = drops::lookup_enemy("rinka").unwrap();
+    assert_eq!(
+        analysis::chance_nothing(rinka, &DropSet::ALL, 3, Rng::RESET, seeds.iter().copied())
+            .unwrap(),
+        1.
+    );
+}
+
+#[test]
+fn explosion_timing() {
+    // `rng.py` models a mini-Kraid kill independently of this crate: four drops, each preceded by
+    // a discarded roll, then 176 rolls while it explodes (sixteen explosions of three rolls, eight
+    // frames apart), then the extra drop. Run from $0061, it leaves the seed at $B094.
+    let minikraid = drops::lookup_enemy("minikraid").unwrap();
+    let mut rng = Rng::RESET;
+    minikraid.roll(&mut rng, &DropSet::ALL).for_each(drop);
+    assert_eq!(rng.seed, 0xb094);
+
+    // For every seed, the script kills a mini-Kraid, and then kills a second one from where the
+    // first left off (or, if the first dropped two big energies, from where the seed enters its
+    // loop). Only seeds 4051 and 28007 drop two big energies the first time, and the second kills
+    // drop no big energy for 64,066 seeds, one for 1,468, and two for 2.
+    let kill = |rng: &mut Rng| {
+        minikraid
+            .roll(rng, &DropSet::ALL)
+            .filter(|&drop| drop == Drop::BigEnergy)
+            .count()
+    };
+    let mut twice = Vec::new();
+    let mut counts = [0; 6];
+    for seed in 0..=0xFFFF {
+        let mut rng = Rng::RESET.with_seed(seed);
+        if kill(&mut rng) == 2 {
+            twice.push(seed);
+            rng = rng.with_seed(Rng::RESET.with_seed(seed).loop_seeds()[0]);
+        }
+        counts[kill(&mut rng)] += 1;
+    }
+    assert_eq!(twice, [4051, 28007]);
+    assert_eq!(counts, [64066, 1468, 2, 0, 0, 0]);
+
+    // A metroid's explosion has the same structure, with five explosions of two rolls. There is no
+    // independent reference for it, so this pins the seed after one kill from $0061 (five main
+    // drops, 50 rolls of explosions, then the extra drop) against accidental changes.
+    let metroid = drops::lookup_enemy("metroid").unwrap();
+    let mut rng = Rng::RESET;
+    metroid.roll(&mut rng, &DropSet::ALL).for_each(drop);
+    assert_eq!(rng.seed, 0x2f0b);
+
+    for (explosion, rolls) in [
+        (ExplosionDrop::Metroid, 50),
+        (ExplosionDrop::Minikraid, 176),
+    ] {
+        let mut rng = Rng::RESET;
+        explosion.simulate(&mut rng, 1);
+        assert_eq!(Some(&rng.seed), Rng::RESET.clone().roll_n(rolls).last());
+    }
+}
+
+#[test]
+fn constructed_table_matches_deserialized() {
+    let metroid = DropTable::new(10, 25, 50, 90, 50, 30)
+        .with_count(5)
+        .with_explosion(ExplosionDrop::Metroid);
+    assert!(metroid == *drops::lookup_enemy("metroid").unwrap());
+
+    let deserialized: DropTable = serde_json::from_str(
+        r#"{
+            "nothing": 60,
+            "small_energy": 60,
+            "big_energy": 5,
+            "missile": 100,
+            "super_missile": 20,
+            "power_bomb": 10,
+            "count": 2,
+            "extra": {
+                "custom": { "num_explosions": 3, "rng_per_explosion": 1, "explosion_interval": 4 }
+            }
+        }"#,
+    )
+    .unwrap();
+    let constructed = DropTable::new(60, 60, 5, 100, 20, 10)
+        .with_count(2)
+        .with_explosion(ExplosionDrop::Custom {
+            num_explosions: 3,
+            rng_per_explosion: 1,
+            explosion_interval: 4,
+        });
+    assert!(constructed == deserialized);
+
+    for seed in (0..=0xFFFFu16).step_by(257) {
+        let mut a = Rng::RESET.with_seed(seed);
+        let mut b = a.clone();
+        assert!(constructed
+            .roll(&mut a, &DropSet::ALL)
+            .eq(deserialized.roll(&mut b, &DropSet::ALL)));
+        assert_eq!(a.seed, b.seed);
+    }
+}
+
+#[test]
+fn register_custom_enemies() {
+    assert!(drops::lookup_enemy("testCustomEnemy").is_none());
+    let custom = DropTable::new(55, 50, 50, 50, 25, 25);
+    drops::register_enemy("testCustomEnemy".to_string(), custom.clone()).unwrap();
+    assert!(*drops::lookup_enemy("testCustomEnemy").unwrap() == custom);
+
+    // Registered enemies take priority over built-in ones. No other test uses zeelas, since this
+    // affects every test in this file.
+    let builtin = &drops::ENEMY_DROPS["zeela"];
+    assert!(*drops::lookup_enemy("zeela").unwrap() == *builtin);
+    let replacement = DropTable::new(255, 0, 0, 0, 0, 0);
+    assert!(replacement != *builtin);
+    drops::register_enemy("zeela".to_string(), replacement.clone()).unwrap();
+    assert!(*drops::lookup_enemy("zeela").unwrap() == replacement);
+    assert!(drops::ENEMY_DROPS["zeela"] == *builtin);
+}
+
+#[test]
+fn malformed_tables_return_errors() {
+    let overflow = DropTable::new(100, 100, 100, 0, 0, 0);
+    let all_zero = DropTable::new(0, 0, 0, 0, 0, 0);
+    for (table, error) in [
+        (&overflow, DropError::MinorOverflow(300)),
+        (&all_zero, DropError::AllZero),
+    ] {
+        let mut rng = Rng::RESET;
+        assert_eq!(
+            table.try_roll_one(&mut rng, &DropSet::ALL),
+            Err(error.clone())
+        );
+        assert!(table.try_roll_multiple(&mut rng, &DropSet::ALL, 2).is_err());
+        assert_eq!(rng.seed, Rng::RESET.seed);
+
+        let seeds = || 0..100u16;
+        assert_eq!(
+            analysis::analyze_correlated(table, &DropSet::ALL, 1, Rng::RESET, seeds()),
+            Err(error.clone())
+        );
+        assert_eq!(
+            analysis::analyze_uncorrelated(table, &DropSet::ALL, 1, seeds()),
+            Err(error.clone())
+        );
+        assert_eq!(
+            analysis::analyze_continuous(table, &DropSet::ALL, 1, Rng::RESET, 100),
+            Err(error.clone())
+        );
+        assert_eq!(
+            analysis::analyze_weighted(
+                table,
+                &DropSet::ALL,
+                1,
+                Rng::RESET,
+                seeds().map(|seed| (seed, 1.))
+            ),
+            Err(error.clone())
+        );
+        assert_eq!(
+            analysis::analyze_route(&[(table, 1)], &DropSet::ALL, Rng::RESET, seeds()),
+            Err(error.clone())
+        );
+        assert_eq!(
+            analysis::histogram(table, &DropSet::ALL, 1, Rng::RESET, seeds(), &DropSet::ALL),
+            Err(error.clone())
+        );
+        assert_eq!(
+            analysis::per_seed(table, &DropSet::ALL, 1, Rng::RESET, seeds()),
+            Err(error.clone())
+        );
+        assert_eq!(
+            analysis::chance_at_least_one(
+                table,
+                &DropSet::ALL,
+                1,
+                Rng::RESET,
+                seeds(),
+                Drop::Missile
+            ),
+            Err(error.clone())
+        );
+        assert_eq!(
+            analysis::chance_nothing(table, &DropSet::ALL, 1, Rng::RESET, seeds()),
+            Err(error.clone())
+        );
+        assert_eq!(
+            analysis::expected_frames_to_drop(
+                table,
+                &DropSet::ALL,
+                Rng::RESET,
+                seeds(),
+                Drop::Missile,
+                60
+            ),
+            Err(error)
+        );
+    }
+}
+
+#[test]
+fn malformed_json_returns_error() {
+    for json in ["", "{", r#"{"ghost": {"nothing": "lots"}}"#, "[1, 2, 3]"] {
+        assert!(
+            matches!(
+                drops::load_enemy_drops(json),
+                Err(drops::LoadError::Json(_))
+            ),
+            "{json:?}"
+        );
+    }
+}
+
+#[test]
+fn expected_frames_to_drop() {
+    let always_energy = DropTable::new(0, 255, 0, 0, 0, 0);
+    let seeds = || (0..=0xFFFF).step_by(257);
+    let expected = |drop, possible: &DropSet| {
+        analysis::expected_frames_to_drop(&always_energy, possible, Rng::RESET, seeds(), drop, 60)
+            .unwrap()
+    };
+
+    // A guaranteed drop always comes from the first kill.
+    assert_eq!(expected(Drop::SmallEnergy, &DropSet::ALL), 60.);
+
+    // A drop that can't be picked up, or that the enemy never drops, never arrives.
+    let full_energy = DropSet::ALL - &DropSet::from_iter([Drop::SmallEnergy]);
+    assert_eq!(expected(Drop::SmallEnergy, &full_energy), f32::INFINITY);
+    assert_eq!(expected(Drop::SuperMissile, &DropSet::ALL), f32::INFINITY);
+}
+
+#[test]
+fn parse_drop_sets() {
+    for (alias, set) in [
+        ("energy", DropSet::ENERGY),
+        ("ammo", DropSet::AMMO),
+        ("minor", DropSet::MINOR),
+        ("major", DropSet::MAJOR),
+        ("all", DropSet::ALL),
+        ("ALL", DropSet::ALL),
+    ] {
+        assert_eq!(alias.parse::<DropSet>(), Ok(set), "{alias}");
+    }
+
+    assert_eq!(
+        "small_energy, missile,super".parse::<DropSet>(),
+        Ok(DropSet::from_iter([
+            Drop::SmallEnergy,
+            Drop::Missile,
+            Drop::SuperMissile
+        ]))
+    );
+    assert_eq!("major,pb".parse::<DropSet>(), Ok(DropSet::MAJOR));
+    assert_eq!(
+        "energy,nothing".parse::<DropSet>(),
+        Ok(DropSet::ENERGY | &DropSet::from_iter([Drop::Nothing]))
+    );
+
+    for invalid in ["", "missile,", "missiles", "energy,bogus"] {
+        assert!(invalid.parse::<DropSet>().is_err(), "{invalid:?}");
+    }
+    assert!("energy,bogus"
+        .parse::<DropSet>()
+        .unwrap_err()
+        .contains("bogus"));
+}
+
+#[test]
+fn exact_drops_differ_from_ideal() {
+    // With power bombs excluded, the pooled minor chance is 160 and the minor drops share the
+    // 205/255 left over after super missiles. Each minor chance scales to 50 * 205 / 160 = 64.0625,
+    // which the game truncates to 64; the unclaimed remainder goes to `Drop::Nothing` instead.
+    let table = DropTable::new(10, 50, 50, 50, 50, 55);
+    let possible = DropSet::ALL - &DropSet::from_iter([Drop::PowerBomb]);
+
+    let ideal = |drop| table.ideal_drops_per_farm(drop, &possible, 255) as f64;
+    let exact = |drop| table.exact_drops_per_farm(drop, &possible, 255);
+    assert_eq!(ideal(Drop::Missile), 64.);
+    assert_eq!(exact(Drop::Missile), 64.0625);
+    assert!(exact(Drop::Nothing) < ideal(Drop::Nothing));
+
+    // Major drops aren't scaled, so they agree.
+    assert_eq!(exact(Drop::SuperMissile), ideal(Drop::SuperMissile));
+    assert_eq!(exact(Drop::PowerBomb), 0.);
+
+    // Both account for every drop.
+    let total = |f: &dyn Fn(Drop) -> f64| Drop::ALL.iter().map(|&drop| f(drop)).sum::<f64>();
+    assert!((total(&ideal) - 255.).abs() < 1e-3);
+    assert!((total(&exact) - 255.).abs() < 1e-9);
+}
+
+#[test]
+fn minor_sum() {
+    let metroid = drops::lookup_enemy("metroid").unwrap();
+    assert_eq!(metroid.minor_sum(), 10 + 25 + 50 + 90);
+    assert_eq!(DropTable::new(255, 0, 0, 0, 100, 100).minor_sum(), 255);
+    assert_eq!(DropTable::new(0, 0, 0, 0, 255, 0).minor_sum(), 0);
+
+    // The minor chances fill whatever the major chances leave, so every built-in table sums to
+    // 255 only once the major chances are included.
+    for (enemy, table) in drops::ENEMY_DROPS.iter() {
+        let total = table.minor_sum() + table.super_missile as u16 + table.power_bomb as u16;
+        assert_eq!(total, 255, "{enemy}");
+    }
+}
+
+#[test]
+fn per_seed_sums_to_correlated() {
+    let table = drops::lookup_enemy("metroid").unwrap();
+    let seeds = || Rng::RESET.loop_seeds();
+
+    let per_seed = analysis::per_seed(table, &DropSet::ALL, 2, Rng::RESET, seeds()).unwrap();
+    assert_eq!(
+        per_seed.iter().map(|(seed, _)| *seed).collect::<Vec<_>>(),
+        seeds()
+    );
+    assert!(per_seed.iter().all(|(_, analysis)| analysis.seeds == 1));
+
+    let total = per_seed
+        .into_iter()
+        .fold(analysis::DropAnalysis::default(), |total, (_, analysis)| {
+            total + analysis
+        });
+    assert_eq!(
+        total,
+        analysis::analyze_correlated(table, &DropSet::ALL, 2, Rng::RESET, seeds()).unwrap()
+    );
+}
+
+#[test]
+fn pooled_chances() {
+    let metroid = drops::lookup_enemy("metroid").unwrap();
+    assert_eq!(metroid.pooled_minor(&DropSet::ALL), 10 + 25 + 50 + 90);
+    assert_eq!(metroid.pooled_major(&DropSet::ALL), 50 + 30);
+
+    // Excluded drops don't contribute to their pool.
+    let possible = DropSet::ALL - &DropSet::from_iter([Drop::Missile, Drop::PowerBomb]);
+    assert_eq!(metroid.pooled_minor(&possible), 10 + 25 + 50);
+    assert_eq!(metroid.pooled_major(&possible), 50);
+
+    assert_eq!(metroid.pooled_minor(&DropSet::MAJOR), 0);
+    assert_eq!(metroid.pooled_major(&DropSet::MINOR), 0);
+    assert_eq!(metroid.pooled_minor(&DropSet::EMPTY), 0);
+}
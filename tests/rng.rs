@@ -0,0 +1,792 @@
+use std::collections::HashSet;
+
+use smrng::{
+    format::{format_count, format_percentage, format_seed},
+    loop_analysis::{Analysis, LoopInfo, SeedInfo},
+    rng::{parse_range, parse_seed, parse_seed_range, rng1, RngParams},
+    Rng,
+};
+
+/// Known outputs of the game's PRNG routine, worked by hand from the disassembly at
+/// https://patrickjohnston.org/bank/80?just=8111 (and cross-checked against `rng.py`).
+///
+/// The routine multiplies each byte of the seed by 5 (keeping only the low byte of the high
+/// product), adds $0100, and then adds $11 plus the carry out of bit 15.
+const RNG1_REFERENCE: [(u16, u16); 10] = [
+    (0x0000, 0x0111),
+    (0x0061, 0x02f6),
+    (0x0017, 0x0184),
+    (0x0025, 0x01ca),
+    (0x0011, 0x0166),
+    (0x00ff, 0x060c),
+    (0x3333, 0x0111),
+    (0x8000, 0x8111),
+    (0xff00, 0xfc11),
+    (0xffff, 0x010d),
+];
+
+#[test]
+fn rng1_matches_reference() {
+    for (seed, expected) in RNG1_REFERENCE {
+        assert_eq!(rng1(seed), expected, "rng1({seed:#06x})");
+    }
+}
+
+#[test]
+fn custom_rng_params() {
+    assert_eq!(RngParams::default(), RngParams::VANILLA);
+    for (seed, expected) in RNG1_REFERENCE {
+        assert_eq!(RngParams::VANILLA.next(seed), expected, "{seed:#06x}");
+    }
+
+    // Worked by hand like `RNG1_REFERENCE`, but multiplying each byte by 3: for example, from
+    // $0061 the low product is $0123, so the result is $0123 + $0100 + $11 = $0234. From $FFFF,
+    // the sum $02FD + $FD00 + $0100 carries out of bit 15, so the carry is added as well.
+    let params = RngParams {
+        multiplier: 3,
+        ..RngParams::VANILLA
+    };
+    let mut rng = Rng {
+        params,
+        ..Rng::RESET
+    };
+    assert_eq!(rng.roll(), 0x0234);
+    assert_eq!(rng.roll(), 0x07ad);
+    assert_eq!(params.next(0xffff), 0x010f);
+
+    let params = RngParams {
+        increment: 0x23,
+        ..RngParams::VANILLA
+    };
+    assert_eq!(params.next(0x0061), 0x0308);
+    assert_ne!(params.next(0x0061), rng1(0x0061));
+}
+
+#[test]
+fn named_state_constants() {
+    assert_eq!((Rng::RESET.seed, Rng::RESET.xba), (0x0061, false));
+    assert_eq!((Rng::BEETOM.seed, Rng::BEETOM.xba), (0x0017, false));
+    assert_eq!((Rng::SIDEHOPPER.seed, Rng::SIDEHOPPER.xba), (0x0025, false));
+    assert_eq!((Rng::POLYP.seed, Rng::POLYP.xba), (0x0011, true));
+    for rng in [Rng::RESET, Rng::BEETOM, Rng::SIDEHOPPER, Rng::POLYP] {
+        assert_eq!(rng.calls_per_frame, 1);
+    }
+}
+
+#[test]
+fn named_states() {
+    assert_eq!(Rng::from_name("reset").unwrap().seed, Rng::RESET.seed);
+    assert_eq!(Rng::from_name("PowerOn").unwrap().seed, Rng::RESET.seed);
+    assert_eq!(Rng::from_name("beetom").unwrap().seed, Rng::BEETOM.seed);
+    assert_eq!(
+        Rng::from_name("sidehopper").unwrap().seed,
+        Rng::SIDEHOPPER.seed
+    );
+    assert_eq!(Rng::from_name("hopper").unwrap().seed, Rng::SIDEHOPPER.seed);
+    assert!(Rng::from_name("0x1234").is_none());
+
+    let polyp = Rng::from_name("polyp").unwrap();
+    assert_eq!(polyp.seed, Rng::POLYP.seed);
+    assert!(polyp.xba);
+}
+
+#[test]
+fn parse_seed_specifiers() {
+    for (name, named) in [
+        ("reset", Rng::RESET),
+        ("power", Rng::RESET),
+        ("PowerOn", Rng::RESET),
+        ("beetom", Rng::BEETOM),
+        ("sidehopper", Rng::SIDEHOPPER),
+        ("Hopper", Rng::SIDEHOPPER),
+        ("polyp", Rng::POLYP),
+    ] {
+        let rng: Rng = name.parse().unwrap();
+        assert_eq!((rng.seed, rng.xba), (named.seed, named.xba), "{name}");
+    }
+
+    for (specifier, seed) in [("0x1234", 0x1234), ("0XABCD", 0xabcd), ("4660", 4660)] {
+        let rng: Rng = specifier.parse().unwrap();
+        assert_eq!(rng.seed, seed, "{specifier}");
+        assert!(!rng.xba);
+        assert_eq!(rng.calls_per_frame, Rng::RESET.calls_per_frame);
+    }
+
+    for invalid in ["", "kraid", "0x", "0x10000", "65536", "-1", "12ab"] {
+        assert!(invalid.parse::<Rng>().is_err(), "{invalid}");
+    }
+}
+
+#[test]
+fn reset_to_named_keeps_configuration() {
+    let mut rng = Rng {
+        calls_per_frame: 3,
+        ..Rng::RESET
+    };
+    assert!(rng.reset_to_named("polyp"));
+    assert_eq!(rng.seed, Rng::POLYP.seed);
+    assert!(rng.xba);
+    assert_eq!(rng.calls_per_frame, 3);
+
+    assert!(rng.reset_to_named("beetom"));
+    assert!(!rng.xba);
+    assert!(!rng.reset_to_named("kraid"));
+    assert_eq!(rng.seed, Rng::BEETOM.seed);
+}
+
+#[test]
+fn analyze_reachable_matches_full_analysis() {
+    let full = Rng::RESET.analyze();
+    let reachable = Rng::RESET.analyze_reachable();
+
+    assert_eq!(reachable.loops.len(), 1);
+    assert_eq!(reachable.loops[0].seeds, full.start_loop().seeds);
+    assert_eq!(reachable.branches.len(), 1);
+    // The full analysis may extend branch 0 backwards with seeds that lead into it.
+    assert!(full.branches[0]
+        .seeds
+        .ends_with(&reachable.branches[0].seeds));
+}
+
+#[test]
+fn shares_loop_with() {
+    let analysis = Rng::RESET.analyze();
+    let loop0 = &analysis.loops[0].seeds;
+    let loop2 = &analysis.loops[2].seeds;
+
+    assert!(analysis.shares_loop_with(loop0[0], loop0[100]));
+    assert!(analysis.shares_loop_with(Rng::RESET.seed, loop0[0]));
+    assert!(!analysis.shares_loop_with(loop0[0], loop2[0]));
+    assert!(!analysis.shares_loop_with(Rng::RESET.seed, loop2[0]));
+}
+
+#[test]
+fn format_seed_modes() {
+    assert_eq!(format_seed(0x0061, true), "0x0061");
+    assert_eq!(format_seed(0x0061, false), "97");
+    assert_eq!(format_seed(0xffff, true), "0xffff");
+    assert_eq!(format_seed(0, false), "0");
+}
+
+#[test]
+fn builder_methods() {
+    let rng = Rng::RESET.with_xba(true).with_calls_per_frame(3);
+    assert_eq!(rng.seed, Rng::RESET.seed);
+    assert!(rng.xba);
+    assert_eq!(rng.calls_per_frame, 3);
+    assert_eq!(rng.params, Rng::RESET.params);
+
+    let rng = rng.with_seed(0x1234).with_xba(false);
+    assert_eq!(rng.seed, 0x1234);
+    assert!(!rng.xba);
+    assert_eq!(rng.calls_per_frame, 3);
+}
+
+#[test]
+fn reset_main_loop() {
+    let analysis = Rng::RESET.analyze();
+    let main_loop = &analysis.loops[0];
+    assert_eq!(main_loop.period(), LoopInfo::MAIN_LOOP_PERIOD);
+    assert_eq!(main_loop.start(), 0x02b0);
+
+    let found = analysis.find_loop_starting_near(Rng::RESET.seed).unwrap();
+    assert_eq!(found.start(), main_loop.start());
+}
+
+#[test]
+fn analysis_serialization_round_trip() {
+    for analysis in [Rng::RESET.analyze(), Rng::POLYP.analyze_reachable()] {
+        let json = serde_json::to_string(&analysis).unwrap();
+        let decoded: Analysis = serde_json::from_str(&json).unwrap();
+
+        assert!(decoded.seeds == analysis.seeds);
+        assert_eq!(serde_json::to_string(&decoded).unwrap(), json);
+    }
+}
+
+#[test]
+fn successors_and_predecessors_agree() {
+    for rng in [Rng::RESET, Rng::POLYP, Rng::RESET.with_calls_per_frame(2)] {
+        let analysis = rng.analyze_reachable();
+        for seed in (0..=0xFFFFu16).step_by(4099) {
+            let successor = analysis.successor_of(seed);
+            assert!(analysis.predecessors_of(successor).contains(&seed));
+            for predecessor in analysis.predecessors_of(seed) {
+                assert_eq!(analysis.successor_of(predecessor), seed);
+            }
+        }
+    }
+}
+
+#[test]
+fn is_in_loop_matches_analysis() {
+    let analysis = Rng::RESET.analyze();
+    let mut seeds: Vec<u16> = (0..=0xFFFF).step_by(1009).collect();
+    seeds.push(Rng::RESET.seed);
+    seeds.extend(analysis.loops.iter().map(|l| l.start()));
+    seeds.extend(analysis.branches.iter().map(|b| b.seeds[0]));
+
+    for seed in seeds {
+        let in_loop = matches!(analysis[seed], SeedInfo::Loop { .. });
+        assert_eq!(
+            Rng::RESET.with_seed(seed).is_in_loop(),
+            in_loop,
+            "{seed:#06x}"
+        );
+    }
+}
+
+#[test]
+fn visit_counts() {
+    let analysis = Rng::RESET.analyze();
+    let visits = analysis.visit_counts();
+
+    for l in &analysis.loops {
+        for &seed in &l.seeds {
+            assert_eq!(visits[seed as usize], l.basin_size);
+        }
+    }
+
+    // Each branch seed is visited by itself and all of its predecessors' visitors.
+    for seed in (0..=0xFFFFu16).step_by(251) {
+        if let SeedInfo::Branch { .. } = analysis[seed] {
+            let upstream: u32 = analysis
+                .predecessors_of(seed)
+                .iter()
+                .map(|&p| visits[p as usize])
+                .sum();
+            assert_eq!(visits[seed as usize], upstream + 1);
+        }
+    }
+}
+
+#[test]
+fn advance_until() {
+    let mut rng = Rng::RESET;
+    let frames = rng.advance_until(|seed| seed & 0xFF == 0).unwrap();
+    assert_eq!(rng.seed & 0xFF, 0);
+    assert_eq!(Rng::RESET.seed_after(frames), rng.seed);
+    assert!(Rng::RESET
+        .seeds_until_loop()
+        .take(frames as usize)
+        .all(|seed| seed & 0xFF != 0));
+
+    let target = Rng::RESET.seed_after(100);
+    let mut rng = Rng::RESET;
+    assert_eq!(
+        rng.advance_until(|seed| seed == target).map(|f| f as usize),
+        Rng::RESET.distance_to(target)
+    );
+
+    let mut rng = Rng::RESET;
+    assert_eq!(rng.advance_until(|_| false), None);
+}
+
+#[test]
+fn loop_seeds() {
+    for rng in [Rng::RESET, Rng::POLYP, Rng::RESET.with_calls_per_frame(0)] {
+        let seeds = rng.loop_seeds();
+        let mut last = rng.with_seed(*seeds.last().unwrap());
+        last.frame_advance();
+        assert_eq!(last.seed, seeds[0]);
+        assert_eq!(seeds.iter().collect::<HashSet<_>>().len(), seeds.len());
+    }
+
+    let analysis = Rng::RESET.analyze();
+    assert_eq!(Rng::RESET.loop_seeds(), analysis.start_loop().seeds);
+}
+
+#[test]
+fn format_counts() {
+    let cases = [
+        (0, "0"),
+        (7, "7"),
+        (999, "999"),
+        (1000, "1,000"),
+        (2280, "2,280"),
+        (65536, "65,536"),
+        (123456, "123,456"),
+        (1234567, "1,234,567"),
+        (u64::MAX, "18,446,744,073,709,551,615"),
+    ];
+    for (count, formatted) in cases {
+        assert_eq!(format_count(count), formatted);
+    }
+}
+
+#[test]
+fn format_percentages() {
+    let cases = [
+        (0, 100, "0.00"),
+        (1, 1000, "0.10"),
+        (7, 2280, "0.31"),
+        (1, 2, "50.0"),
+        (1, 1, " 100"),
+        // Just below and at the rounding boundaries, which would otherwise gain a digit.
+        (9_994, 100_000, "9.99"),
+        (9_995, 100_000, "10.0"),
+        (99_949, 100_000, "99.9"),
+        (99_950, 100_000, " 100"),
+    ];
+    for (num, denom, formatted) in cases {
+        assert_eq!(format_percentage(num, denom), formatted, "{num}/{denom}");
+    }
+
+    for num in 0..=10_000 {
+        assert_eq!(format_percentage(num, 10_000).len(), 4, "{num}/10000");
+    }
+}
+
+#[test]
+fn mirror_loops() {
+    assert!(Rng::RESET.analyze().mirror_loops().is_empty());
+
+    // Find mirror pairs independently, by comparing each loop's byte-swapped seeds with every
+    // other loop's seeds.
+    let expected_mirrors = |analysis: &Analysis| {
+        let sorted = |seeds: &mut dyn Iterator<Item = u16>| {
+            let mut seeds: Vec<u16> = seeds.collect();
+            seeds.sort();
+            seeds
+        };
+        let loops: Vec<Vec<u16>> = analysis
+            .loops
+            .iter()
+            .map(|l| sorted(&mut l.seeds.iter().copied()))
+            .collect();
+        let mut mirrors = Vec::new();
+        for (a, seeds) in loops.iter().enumerate() {
+            let swapped = sorted(&mut seeds.iter().map(|seed| seed.swap_bytes()));
+            for (b, other) in loops.iter().enumerate().skip(a + 1) {
+                if *other == swapped {
+                    mirrors.push((a, b));
+                }
+            }
+        }
+        mirrors
+    };
+
+    // None of the XBA loops in the polyp room happen to be mirror images of each other; the
+    // byte swap doesn't commute with the RNG, so the relationship is rare.
+    for calls_per_frame in 1..=3 {
+        let analysis = Rng::POLYP.with_calls_per_frame(calls_per_frame).analyze();
+        let mirrors = analysis.mirror_loops();
+        assert_eq!(mirrors, expected_mirrors(&analysis), "{calls_per_frame}");
+        assert_eq!(mirrors.len(), 0, "{calls_per_frame}");
+    }
+}
+
+#[test]
+fn parse_seed_ranges() {
+    assert_eq!(parse_seed_range("0x2000..0x2100"), Ok(0x2000..=0x20ff));
+    assert_eq!(parse_seed_range("0x2000..=0x2100"), Ok(0x2000..=0x2100));
+    assert_eq!(parse_seed_range("10..20"), Ok(10..=19));
+    assert_eq!(parse_seed_range("10..=20"), Ok(10..=20));
+    assert_eq!(parse_seed_range("0x10..=20"), Ok(0x10..=20));
+    assert_eq!(parse_seed_range("0..=0xFFFF"), Ok(0..=0xffff));
+    assert_eq!(parse_seed_range("7..=7"), Ok(7..=7));
+
+    for invalid in [
+        "",
+        "10",
+        "10..",
+        "..10",
+        "7..7",
+        "0..0",
+        "8..=7",
+        "0x..1",
+        "0..=65536",
+    ] {
+        assert!(parse_seed_range(invalid).is_err(), "{invalid}");
+    }
+}
+
+#[test]
+fn output_histogram() {
+    let period = Rng::RESET.loop_seeds().len() as u64;
+    for buckets in [1, 2, 7, 256, 0x10000] {
+        let histogram = Rng::RESET.output_histogram(buckets);
+        assert_eq!(histogram.len(), buckets);
+        assert_eq!(histogram.iter().sum::<u64>(), period);
+    }
+
+    // With one bucket per seed, each seed of the loop is counted once.
+    let histogram = Rng::RESET.output_histogram(0x10000);
+    for seed in Rng::RESET.loop_seeds() {
+        assert_eq!(histogram[seed as usize], 1);
+    }
+}
+
+#[test]
+fn analyze_classifies_every_seed_for_unusual_configs() {
+    for calls_per_frame in [2, 3, 17, 255, 1000] {
+        for xba in [false, true] {
+            let rng = Rng::RESET
+                .with_calls_per_frame(calls_per_frame)
+                .with_xba(xba);
+            let analysis = rng.analyze();
+            assert!(
+                (0..=0xFFFFu16).all(|seed| analysis[seed] != SeedInfo::Unclassified),
+                "{rng:?}"
+            );
+
+            let basins: u32 = analysis.loops.iter().map(|l| l.basin_size).sum();
+            assert_eq!(basins, 0x10000, "{rng:?}");
+        }
+    }
+}
+
+#[test]
+fn seed_info_accessors() {
+    let looped = SeedInfo::Loop { id: 3 };
+    assert!(looped.is_loop());
+    assert!(!looped.is_branch());
+    assert_eq!(looped.id(), Some(3));
+
+    let branch = SeedInfo::Branch { id: 7 };
+    assert!(!branch.is_loop());
+    assert!(branch.is_branch());
+    assert_eq!(branch.id(), Some(7));
+
+    let unclassified = SeedInfo::Unclassified;
+    assert!(!unclassified.is_loop());
+    assert!(!unclassified.is_branch());
+    assert_eq!(unclassified.id(), None);
+}
+
+#[test]
+fn cached_predecessor_map() {
+    for rng in [Rng::RESET, Rng::POLYP, Rng::RESET.with_calls_per_frame(3)] {
+        let analysis = rng.analyze();
+        let predecessors = analysis.predecessor_map();
+        assert_eq!(predecessors.len(), 0x10000);
+        for seed in 0..=0xFFFFu16 {
+            for &predecessor in &predecessors[seed as usize] {
+                let mut advanced = rng.with_seed(predecessor);
+                advanced.frame_advance();
+                assert_eq!(advanced.seed, seed);
+            }
+        }
+
+        // The map is only built once.
+        assert!(std::ptr::eq(predecessors, analysis.predecessor_map()));
+    }
+}
+
+#[test]
+fn roll_range() {
+    let mut rng = Rng::RESET;
+    let mut expected = Rng::RESET;
+    for _ in 0..100 {
+        let value = rng.roll_range(6);
+        assert_eq!(value, expected.roll() % 6);
+        assert_eq!(rng.seed, expected.seed);
+    }
+
+    // Over the main loop, every value in the range comes up about equally often.
+    for n in [2, 3, 6, 8, 16] {
+        let seeds = Rng::RESET.loop_seeds();
+        let mut counts = vec![0usize; n as usize];
+        for &seed in &seeds {
+            let value = Rng::RESET.with_seed(seed).roll_range(n);
+            assert!(value < n);
+            counts[value as usize] += 1;
+        }
+        let mean = seeds.len() / n as usize;
+        for (value, &count) in counts.iter().enumerate() {
+            assert!(
+                count.abs_diff(mean) <= mean / 2,
+                "{n}: {value} came up {count} times"
+            );
+        }
+    }
+}
+
+#[test]
+fn analysis_diff() {
+    let plain = Rng::RESET.analyze();
+    assert_eq!(plain.diff(&plain).changed(), 0);
+
+    let xba = Rng::RESET.with_xba(true).analyze();
+    let diff = plain.diff(&xba);
+    assert!(diff.changed() > 0);
+    for &seed in &diff.loop_to_branch {
+        assert!(plain[seed].is_loop() && xba[seed].is_branch());
+    }
+    for &seed in &diff.branch_to_loop {
+        assert!(plain[seed].is_branch() && xba[seed].is_loop());
+    }
+
+    let examples = diff.examples(5);
+    assert_eq!(examples.len(), diff.changed().min(5));
+    assert!(examples.windows(2).all(|w| w[0] < w[1]));
+
+    // Diffing in the other direction swaps the lists.
+    let reversed = xba.diff(&plain);
+    assert_eq!(reversed.loop_to_branch, diff.branch_to_loop);
+    assert_eq!(reversed.branch_to_loop, diff.loop_to_branch);
+}
+
+#[test]
+fn loop_phase_difference() {
+    let analysis = Rng::RESET.analyze();
+    let start = analysis.start_loop().start();
+    let later = Rng::RESET.with_seed(start).seed_after(100);
+
+    assert_eq!(analysis.loop_phase_difference(later, start), Some(100));
+    assert_eq!(
+        analysis.loop_phase_difference(start, later),
+        Some(2280 - 100)
+    );
+    assert_eq!(analysis.loop_phase_difference(start, start), Some(0));
+
+    // The reset seed is on a branch, and loop 1 is a different loop.
+    assert_eq!(analysis.loop_phase_difference(Rng::RESET.seed, start), None);
+    let other = analysis.loops[1].start();
+    assert_eq!(analysis.loop_phase_difference(other, start), None);
+}
+
+#[test]
+fn best_reachable_seed() {
+    let analysis = Rng::RESET.analyze();
+    let reachable: Vec<u16> = Rng::RESET.seeds_until_loop().take(101).collect();
+    let best = analysis.best_reachable_seed(Rng::RESET.seed, 100, |seed| seed as f32);
+    assert_eq!(best, reachable.iter().copied().max());
+
+    // Only the starting seed is reachable in 0 frames.
+    assert_eq!(
+        analysis.best_reachable_seed(Rng::RESET.seed, 0, |seed| seed as f32),
+        Some(Rng::RESET.seed)
+    );
+    // Ties go to the seed reached first, and NaN scores are ignored.
+    assert_eq!(
+        analysis.best_reachable_seed(Rng::RESET.seed, 100, |_| 0.),
+        Some(Rng::RESET.seed)
+    );
+    assert_eq!(
+        analysis.best_reachable_seed(Rng::RESET.seed, 100, |_| f32::NAN),
+        None
+    );
+
+    // Searching far enough finds the best seed in the whole loop.
+    let best = analysis.best_reachable_seed(Rng::RESET.seed, u64::MAX, |seed| seed as f32);
+    assert_eq!(best, Rng::RESET.seeds_until_loop().max());
+}
+
+#[test]
+fn apply_and_toggle_xba() {
+    let mut rng = Rng::RESET.with_seed(0x1234);
+    rng.apply_xba();
+    assert_eq!(rng.seed, 0x3412);
+    rng.apply_xba();
+    assert_eq!(rng.seed, 0x1234);
+
+    // Toggling XBA partway through matches advancing each part with its own configuration.
+    let mut toggled = Rng::RESET;
+    toggled.frame_advance();
+    toggled.toggle_xba();
+    assert!(toggled.xba);
+    toggled.frame_advance();
+    toggled.toggle_xba();
+    assert!(!toggled.xba);
+
+    let mut expected = Rng::RESET;
+    expected.frame_advance();
+    expected.roll();
+    expected.apply_xba();
+    assert_eq!(toggled.seed, expected.seed);
+}
+
+#[test]
+fn reset_stats() {
+    let analysis = Rng::RESET.analyze();
+    let stats = analysis.stats();
+    assert_eq!(stats.loop_seeds + stats.branch_seeds, 0x10000);
+    assert_eq!(stats.loops, analysis.loops.len());
+    assert_eq!(stats.longest_period, LoopInfo::MAIN_LOOP_PERIOD);
+    assert!(stats.shortest_period <= stats.longest_period);
+}
+
+#[test]
+fn seeds_reaching() {
+    let analysis = Rng::RESET.analyze();
+    let target = Rng::RESET.seed_after(20);
+
+    assert_eq!(analysis.seeds_reaching(target, 0), [target]);
+
+    let within = 10;
+    let found = analysis.seeds_reaching(target, within);
+    assert_eq!(found[0], target);
+    assert!(found.len() > 1);
+    for &seed in &found {
+        let rng = Rng::RESET.with_seed(seed);
+        let distance = rng.distance_to(target).unwrap() as u64;
+        assert!(distance <= within, "{seed:#06x} takes {distance} frames");
+        assert_eq!(rng.seed_after(distance), target);
+    }
+
+    // Every seed within range is found.
+    let successors = Rng::RESET.successor_map();
+    let expected = (0..=0xFFFFu16)
+        .filter(|&seed| {
+            let mut seed = seed;
+            for _ in 0..=within {
+                if seed == target {
+                    return true;
+                }
+                seed = successors[seed as usize];
+            }
+            false
+        })
+        .count();
+    assert_eq!(found.len(), expected);
+}
+
+#[test]
+fn seed_after_matches_frame_advance() {
+    for xba in [false, true] {
+        let start = Rng::RESET.with_xba(xba);
+        let mut rng = start.clone();
+        let mut frames = 0;
+        for n in [0, 1, 7, 1000, 0x10000, 0x10001, 100_000] {
+            while frames < n {
+                rng.frame_advance();
+                frames += 1;
+            }
+            assert_eq!(start.seed_after(n), rng.seed, "xba = {xba}, n = {n}");
+        }
+    }
+}
+
+#[test]
+fn zero_calls_per_frame() {
+    // Without XBA, the seed never changes, so it's immediately a loop of period 1.
+    let rng = Rng::RESET.with_calls_per_frame(0);
+    assert_eq!(rng.seeds_until_loop().collect::<Vec<_>>(), [rng.seed]);
+    assert_eq!(rng.seed_after(1000), rng.seed);
+    assert!(rng.is_in_loop());
+
+    // With XBA, the seed alternates between its two byte orders.
+    let rng = rng.with_xba(true).with_seed(0x1234);
+    assert_eq!(rng.seeds_until_loop().collect::<Vec<_>>(), [0x1234, 0x3412]);
+}
+
+#[test]
+fn roll_n_matches_roll() {
+    let mut batch = Rng::RESET.with_xba(true).with_calls_per_frame(3);
+    let mut manual = batch.clone();
+    let rolls = batch.roll_n(3);
+    assert_eq!(rolls, [manual.roll(), manual.roll(), manual.roll()]);
+    assert_eq!(batch.seed, rolls[2]);
+    assert_eq!(batch.seed, manual.seed);
+
+    assert!(batch.roll_n(0).is_empty());
+    assert_eq!(batch.seed, manual.seed);
+}
+
+#[test]
+fn index_analysis_by_seed() {
+    let analysis = Rng::RESET.analyze();
+    assert!(analysis[0x0061] == analysis.seeds[0x61]);
+    assert!(analysis[0x0000] == analysis.seeds[0]);
+    assert!(analysis[0xFFFF] == analysis.seeds[0xFFFF]);
+    assert!(analysis[0xFFFF] != SeedInfo::Unclassified);
+
+    let reachable = Rng::RESET.analyze_reachable();
+    assert!(reachable[0xFFFF] == reachable.seeds[0xFFFF]);
+}
+
+#[test]
+fn enumerated_frames() {
+    for rng in [Rng::RESET, Rng::POLYP.with_calls_per_frame(2)] {
+        let frames: Vec<(u64, u16)> = rng.enumerated_frames().take(100).collect();
+        assert_eq!(frames[0], (0, rng.seed));
+
+        let mut advanced = rng.clone();
+        advanced.frame_advance();
+        assert_eq!(frames[1], (1, advanced.seed));
+
+        for (frame, seed) in frames {
+            assert_eq!(seed, rng.seed_after(frame));
+        }
+    }
+}
+
+#[test]
+fn prefix_len() {
+    assert_eq!(
+        Rng::BEETOM.prefix_len(),
+        Rng::BEETOM.seeds_until_loop().count()
+    );
+    for rng in [Rng::RESET, Rng::POLYP, Rng::RESET.with_calls_per_frame(0)] {
+        assert_eq!(rng.prefix_len(), rng.seeds_until_loop().count());
+    }
+}
+
+#[test]
+fn parse_seeds() {
+    for (s, seed) in [
+        ("0x0061", 0x61),
+        ("0X61", 0x61),
+        ("0xABcd", 0xabcd),
+        ("97", 97),
+    ] {
+        assert_eq!(parse_seed(s), Ok(seed), "{s}");
+        assert_eq!(s.parse::<Rng>().unwrap().seed, seed, "{s}");
+    }
+    for invalid in ["", "0x", "0x10000", "65536", "-1", "12ab", "reset"] {
+        assert!(parse_seed(invalid).is_err(), "{invalid}");
+    }
+}
+
+#[test]
+fn cycle_of_matches_analysis() {
+    for rng in [Rng::RESET, Rng::POLYP] {
+        let analysis = rng.analyze();
+        let mut seeds: Vec<u16> = analysis.branches.iter().map(|b| b.seeds[0]).collect();
+        seeds.extend(analysis.branches.iter().map(|b| *b.seeds.last().unwrap()));
+        seeds.extend(analysis.loops.iter().map(|l| l.start()));
+        seeds.extend((0..=0xFFFF).step_by(4099));
+
+        for seed in seeds {
+            // Count the frames until the seed enters the loop.
+            let mut tail = 0;
+            let mut cur = rng.with_seed(seed);
+            while !matches!(analysis[cur.seed], SeedInfo::Loop { .. }) {
+                cur.frame_advance();
+                tail += 1;
+            }
+            let loop_id = analysis.loop_id(seed).unwrap() as usize;
+            let period = analysis.loops[loop_id].seeds.len() as u64;
+            assert_eq!(rng.cycle_of(seed), (tail, period), "{seed:#06x}");
+        }
+    }
+
+    // Without XBA, zero calls per frame leaves every seed in a loop of its own.
+    let rng = Rng::RESET.with_calls_per_frame(0);
+    for seed in [0x0000, 0x0061, 0xFFFF] {
+        assert_eq!(rng.cycle_of(seed), (0, 1));
+    }
+}
+
+#[test]
+fn main_loop_has_largest_basin() {
+    let analysis = Rng::RESET.analyze();
+    assert_eq!(analysis.loops[0].period(), LoopInfo::MAIN_LOOP_PERIOD);
+    assert_eq!(
+        analysis.loops.iter().map(|l| l.basin_size).max(),
+        Some(analysis.loops[0].basin_size)
+    );
+    assert_eq!(analysis.stats().largest_basin, 0);
+}
+
+#[test]
+fn parse_generic_ranges() {
+    let parse = |s| parse_range(s, |bound| bound.parse::<usize>().map_err(|e| e.to_string()));
+    assert_eq!(parse("1..4"), Ok(1..=3));
+    assert_eq!(parse(" 1 ..= 3 "), Ok(1..=3));
+    assert_eq!(parse("0..1"), Ok(0..=0));
+    for invalid in ["1", "3..3", "0..0", "4..=3", "a..3", "1..b"] {
+        assert!(parse(invalid).is_err(), "{invalid}");
+    }
+}
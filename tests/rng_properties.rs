@@ -0,0 +1,76 @@
+use std::collections::HashSet;
+
+use proptest::prelude::*;
+use smrng::{loop_analysis::SeedInfo, Rng};
+
+/// Generates an arbitrary RNG configuration with the vanilla RNG parameters.
+fn any_rng() -> impl Strategy<Value = Rng> {
+    (any::<u16>(), any::<bool>(), 0..4usize).prop_map(|(seed, xba, calls_per_frame)| Rng {
+        seed,
+        xba,
+        calls_per_frame,
+        ..Rng::RESET
+    })
+}
+
+proptest! {
+    #[test]
+    fn frame_advance_is_deterministic(rng in any_rng()) {
+        let mut a = rng.clone();
+        let mut b = rng.clone();
+        a.frame_advance();
+        b.frame_advance();
+        prop_assert_eq!(a.seed, b.seed);
+    }
+
+    #[test]
+    fn seeds_until_loop_never_repeats(rng in any_rng()) {
+        let mut seen = HashSet::new();
+        for seed in rng.seeds_until_loop() {
+            prop_assert!(seen.insert(seed), "seed {:#06x} repeated", seed);
+        }
+        prop_assert_eq!(seen.len(), rng.prefix_len());
+    }
+}
+
+proptest! {
+    // These examine every seed, so only try a handful of configurations.
+    #![proptest_config(ProptestConfig::with_cases(8))]
+
+    #[test]
+    fn every_seed_has_one_successor(rng in any_rng()) {
+        let predecessors = rng.predecessor_map();
+        let total: usize = predecessors.iter().map(Vec::len).sum();
+        prop_assert_eq!(total, 0x10000);
+
+        let mut seen = HashSet::new();
+        for &seed in predecessors.iter().flatten() {
+            prop_assert!(seen.insert(seed), "seed {:#06x} has multiple successors", seed);
+        }
+    }
+
+    #[test]
+    fn analyze_classifies_every_seed(rng in any_rng()) {
+        let analysis = rng.analyze();
+        prop_assert_eq!(analysis.seeds.len(), 0x10000);
+
+        for (id, l) in analysis.loops.iter().enumerate() {
+            let expected = SeedInfo::Loop { id: id as u16 };
+            for &seed in &l.seeds {
+                prop_assert!(analysis[seed] == expected, "loop seed {:#06x} misclassified", seed);
+            }
+        }
+        for (id, branch) in analysis.branches.iter().enumerate() {
+            prop_assert!((branch.loop_id as usize) < analysis.loops.len());
+            let expected = SeedInfo::Branch { id: id as u16 };
+            for &seed in &branch.seeds {
+                prop_assert!(analysis[seed] == expected, "branch seed {:#06x} misclassified", seed);
+            }
+        }
+
+        // Together with the checks above, this ensures each seed is listed exactly once.
+        let loop_seeds: usize = analysis.loops.iter().map(|l| l.seeds.len()).sum();
+        let branch_seeds: usize = analysis.branches.iter().map(|b| b.seeds.len()).sum();
+        prop_assert_eq!(loop_seeds + branch_seeds, 0x10000);
+    }
+}